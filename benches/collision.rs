@@ -0,0 +1,236 @@
+use std::path::Path;
+
+use bevy::prelude::*;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use stroids::{
+    check_for_collision, check_world_hull_collision, load_image_from_disk, Hull, HullOptions,
+    WorldHull,
+};
+
+fn sprite_hull(image: &str, options: &HullOptions) -> Hull {
+    let image = load_image_from_disk(&Path::new("assets").join(image));
+    Hull::new(&image, options)
+        .expect("asset sprites have hulls")
+        .sized(image.size_f32())
+}
+
+/// The collision test as it was before world hulls were cached: each hull's vertices rotated into
+/// a new `Vec` and its normals worked out into another for every test. Kept as a baseline for
+/// the cached version to be measured against.
+mod baseline {
+    use super::*;
+
+    fn hull_at_orientation(piece: &[Vec2], transform: &Transform) -> Vec<Vec2> {
+        let mut vertices = Vec::<Vec2>::new();
+        for v in piece {
+            let v_oriented = (transform.rotation * v.extend(0.0)) + transform.translation;
+            vertices.push(v_oriented.truncate());
+        }
+        vertices
+    }
+
+    fn get_all_normals(pts: &[Vec2]) -> Vec<Vec2> {
+        let mut normals = Vec::<Vec2>::new();
+        for edge in pts.windows(2) {
+            normals.push((edge[0] - edge[1]).perp().normalize())
+        }
+        normals
+    }
+
+    fn project(shape: &[Vec2], axis: Vec2) -> (f32, f32) {
+        shape
+            .iter()
+            .map(|v| axis.dot(*v))
+            .fold((f32::MAX, f32::MIN), |(min, max), p| {
+                (min.min(p), max.max(p))
+            })
+    }
+
+    fn check_piece_collision(vertices_a: &[Vec2], vertices_b: &[Vec2]) -> bool {
+        get_all_normals(vertices_a)
+            .into_iter()
+            .chain(get_all_normals(vertices_b))
+            .all(|axis| {
+                let (min_a, max_a) = project(vertices_a, axis);
+                let (min_b, max_b) = project(vertices_b, axis);
+                min_a <= max_b && max_a >= min_b
+            })
+    }
+
+    pub fn check_for_collision(
+        hull_a: &Hull,
+        hull_a_transform: &Transform,
+        hull_b: &Hull,
+        hull_b_transform: &Transform,
+    ) -> bool {
+        let distance_squared = hull_a_transform
+            .translation
+            .truncate()
+            .distance_squared(hull_b_transform.translation.truncate());
+        let reach = hull_a.radius() + hull_b.radius();
+        if distance_squared > reach * reach {
+            return false;
+        }
+
+        hull_a.pieces().iter().any(|piece_a| {
+            let vertices_a = hull_at_orientation(piece_a, hull_a_transform);
+            hull_b.pieces().iter().any(|piece_b| {
+                check_piece_collision(&vertices_a, &hull_at_orientation(piece_b, hull_b_transform))
+            })
+        })
+    }
+}
+
+fn transform_at(position: Vec2, rotation: f32) -> Transform {
+    Transform::from_translation(position.extend(0.)).with_rotation(Quat::from_rotation_z(rotation))
+}
+
+/// A square grid of `count` hulls spaced so neighbours overlap, turned at different angles.
+fn field(hull: &Hull, count: usize, spacing: f32) -> Vec<(Hull, Transform)> {
+    let side = (count as f32).sqrt().ceil() as usize;
+    (0..count)
+        .map(|i| {
+            let position = Vec2::new((i % side) as f32, (i / side) as f32) * spacing;
+            (hull.clone(), transform_at(position, i as f32 * 0.7))
+        })
+        .collect()
+}
+
+/// One overlapping asteroid and ship pair, so every test runs the full SAT rather than stopping
+/// at the bounding circles.
+fn pair(c: &mut Criterion) {
+    let asteroid = sprite_hull("asteroid-lg.png", &HullOptions::default());
+    let ship = sprite_hull("ship.png", &HullOptions::default());
+    let asteroid_transform = transform_at(Vec2::ZERO, 0.3);
+    let ship_transform = transform_at(Vec2::new(40., 20.), 1.2);
+
+    let mut group = c.benchmark_group("pair");
+    group.bench_function("allocating_baseline", |b| {
+        b.iter(|| {
+            baseline::check_for_collision(
+                black_box(&asteroid),
+                black_box(&asteroid_transform),
+                black_box(&ship),
+                black_box(&ship_transform),
+            )
+        })
+    });
+
+    group.bench_function("transform_per_test", |b| {
+        b.iter(|| {
+            check_for_collision(
+                black_box(&asteroid),
+                black_box(&asteroid_transform),
+                black_box(&ship),
+                black_box(&ship_transform),
+            )
+        })
+    });
+
+    let asteroid_world = WorldHull::new(&asteroid, &asteroid_transform);
+    let ship_world = WorldHull::new(&ship, &ship_transform);
+    group.bench_function("cached_world_hulls", |b| {
+        b.iter(|| check_world_hull_collision(black_box(&asteroid_world), black_box(&ship_world)))
+    });
+
+    // the ship traced concave, following its outline in convex pieces
+    let concave_ship = sprite_hull(
+        "ship.png",
+        &HullOptions {
+            max_error: 1.,
+            concave: true,
+            ..HullOptions::default()
+        },
+    );
+    let concave_ship_world = WorldHull::new(&concave_ship, &ship_transform);
+    group.bench_function("cached_world_hulls_concave_ship", |b| {
+        b.iter(|| {
+            check_world_hull_collision(black_box(&asteroid_world), black_box(&concave_ship_world))
+        })
+    });
+    group.finish();
+}
+
+/// Every asteroid against every other, as one tick would with no broad phase. The cached variant
+/// includes refreshing each world hull once, as `update_world_hulls` does every tick.
+fn all_pairs(c: &mut Criterion) {
+    let asteroid = sprite_hull("asteroid-m.png", &HullOptions::default());
+
+    let mut group = c.benchmark_group("all_pairs");
+    for count in [16, 64, 256] {
+        let hulls = field(&asteroid, count, 48.);
+
+        group.bench_with_input(
+            BenchmarkId::new("allocating_baseline", count),
+            &hulls,
+            |b, hulls| {
+                b.iter(|| {
+                    let mut hits = 0;
+                    for (i, (hull_a, transform_a)) in hulls.iter().enumerate() {
+                        for (hull_b, transform_b) in &hulls[i + 1..] {
+                            if baseline::check_for_collision(
+                                hull_a,
+                                transform_a,
+                                hull_b,
+                                transform_b,
+                            ) {
+                                hits += 1;
+                            }
+                        }
+                    }
+                    hits
+                })
+            },
+        );
+
+        group.bench_with_input(
+            BenchmarkId::new("transform_per_test", count),
+            &hulls,
+            |b, hulls| {
+                b.iter(|| {
+                    let mut hits = 0;
+                    for (i, (hull_a, transform_a)) in hulls.iter().enumerate() {
+                        for (hull_b, transform_b) in &hulls[i + 1..] {
+                            if check_for_collision(hull_a, transform_a, hull_b, transform_b)
+                                .is_some()
+                            {
+                                hits += 1;
+                            }
+                        }
+                    }
+                    hits
+                })
+            },
+        );
+
+        let mut world_hulls: Vec<WorldHull> = hulls
+            .iter()
+            .map(|(hull, transform)| WorldHull::new(hull, transform))
+            .collect();
+        group.bench_with_input(
+            BenchmarkId::new("cached_world_hulls", count),
+            &hulls,
+            |b, hulls| {
+                b.iter(|| {
+                    for ((hull, transform), world_hull) in hulls.iter().zip(&mut world_hulls) {
+                        world_hull.update(hull, transform);
+                    }
+
+                    let mut hits = 0;
+                    for (i, hull_a) in world_hulls.iter().enumerate() {
+                        for hull_b in &world_hulls[i + 1..] {
+                            if check_world_hull_collision(hull_a, hull_b).is_some() {
+                                hits += 1;
+                            }
+                        }
+                    }
+                    hits
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, pair, all_pairs);
+criterion_main!(benches);
@@ -1,28 +1,48 @@
-use crate::constants::*;
+use crate::physics::Arena;
 use crate::physics::Heading;
+use crate::physics::Mass;
 use crate::physics::RotationalVelocity;
 use crate::physics::Velocity;
-use crate::screen_edge_distance;
+use crate::ArenaBoundary;
 use crate::CollisionHulls;
 use crate::Despawning;
 use crate::GameAssets;
-use crate::Hull;
+use crate::HullAsset;
+use crate::InGameSet;
 use crate::Level;
+use crate::Playing;
+use crate::Tuning;
+use crate::WorldHull;
 use bevy::prelude::*;
 use std::f32::consts::TAU;
 
 use bevy_prng::WyRand;
-use bevy_rand::prelude::GlobalEntropy;
+use bevy_rand::prelude::EntropyComponent;
 use rand_core::RngCore;
 
 const ASTEROID_COLOR: Color = Color::WHITE;
-const NUM_ASTEROIDS: u32 = 1;
-const MAX_ASTEROID_SPEED: f32 = MAX_SPEED / 8.;
-const MAX_ASTEROID_ROTATION_SPEED: f32 = TAU * 0.5;
-const SAFE_RADIUS: f32 = 200.;
-const LARGE_ASTEROID_SIZE: Vec2 = Vec2::new(128., 128.);
-const MEDIUM_ASTEROID_SIZE: Vec2 = Vec2::new(64., 64.);
-const SMALL_ASTEROID_SIZE: Vec2 = Vec2::new(32., 32.);
+
+pub struct AsteroidPlugin;
+
+impl Plugin for AsteroidPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<AsteroidDestroyedEvent>()
+            .add_systems(Startup, setup_asteroid_count)
+            .add_systems(OnEnter(Playing), setup_asteroids)
+            .add_systems(
+                FixedUpdate,
+                asteroid_destroyed_listener.in_set(InGameSet::Spawn),
+            );
+    }
+}
+
+/// RNG stream for laying out a new asteroid field.
+#[derive(Resource, Deref, DerefMut)]
+pub struct AsteroidFieldRng(pub EntropyComponent<WyRand>);
+
+/// RNG stream for the spin of asteroids split off a destroyed one.
+#[derive(Resource, Deref, DerefMut)]
+pub struct AsteroidSplitRng(pub EntropyComponent<WyRand>);
 
 #[derive(Event)]
 pub struct AsteroidDestroyedEvent(pub Entity, pub Transform, pub Velocity, pub AsteroidSize);
@@ -48,10 +68,14 @@ struct AsteroidBundle {
     rotational_velocity: RotationalVelocity,
     heading: Heading,
     sprite_bundle: SpriteBundle,
-    hull: Hull,
+    hull: Handle<HullAsset>,
+    world_hull: WorldHull,
+    boundary: ArenaBoundary,
+    mass: Mass,
 }
 
 impl AsteroidBundle {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         size: AsteroidSize,
         position: Vec2,
@@ -60,26 +84,34 @@ impl AsteroidBundle {
         heading: f32,
         game_assets: &GameAssets,
         collision_hulls: &CollisionHulls,
+        hull_assets: &Assets<HullAsset>,
+        tuning: &Tuning,
     ) -> AsteroidBundle {
+        let (hull, sprite_size) = match size {
+            AsteroidSize::Small => (&collision_hulls.asteroid_sm, tuning.asteroids.small_size),
+            AsteroidSize::Medium => (&collision_hulls.asteroid_m, tuning.asteroids.medium_size),
+            AsteroidSize::Large => (&collision_hulls.asteroid_lg, tuning.asteroids.large_size),
+        };
+        // heavier the more of the sprite the hull covers, at the size it's drawn
+        let mass = hull_assets
+            .get(hull)
+            .map(|hull_asset| Mass::from_hull(&hull_asset.sized(sprite_size)))
+            .expect("collision hulls are ready before any asteroid spawns");
+
         AsteroidBundle {
             asteroid: Asteroid,
             size,
             velocity: Velocity(velocity),
             rotational_velocity: RotationalVelocity(rotational_velocity),
             heading: Heading(heading),
-            hull: match size {
-                AsteroidSize::Small => collision_hulls.asteroid_sm.clone(),
-                AsteroidSize::Medium => collision_hulls.asteroid_m.clone(),
-                AsteroidSize::Large => collision_hulls.asteroid_lg.clone(),
-            },
+            mass,
+            hull: hull.clone(),
+            world_hull: WorldHull::default(),
+            boundary: ArenaBoundary::default(),
             sprite_bundle: SpriteBundle {
                 sprite: Sprite {
                     color: ASTEROID_COLOR,
-                    custom_size: match size {
-                        AsteroidSize::Small => Some(SMALL_ASTEROID_SIZE),
-                        AsteroidSize::Medium => Some(MEDIUM_ASTEROID_SIZE),
-                        AsteroidSize::Large => Some(LARGE_ASTEROID_SIZE),
-                    },
+                    custom_size: Some(sprite_size),
                     ..default()
                 },
                 texture: match size {
@@ -101,17 +133,24 @@ pub fn setup_asteroid_count(mut commands: Commands) {
     commands.spawn(AsteroidCount(0));
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn setup_asteroids(
     mut commands: Commands,
-    mut rng: ResMut<GlobalEntropy<WyRand>>,
+    mut rng: ResMut<AsteroidFieldRng>,
     game_assets: Res<GameAssets>,
     collision_hulls: Res<CollisionHulls>,
+    hull_assets: Res<Assets<HullAsset>>,
     mut asteroid_count_query: Query<&mut AsteroidCount>,
     level_query: Query<&Level>,
+    arena: Res<Arena>,
+    tuning: Res<Tuning>,
 ) {
     let level = level_query.single();
     let mut asteroid_count = asteroid_count_query.single_mut();
-    let num_asteroids = NUM_ASTEROIDS + (2 * level.0);
+    let num_asteroids = tuning.asteroids.count_for_level(level.0);
+    let safe_radius = tuning.asteroids.safe_radius;
+    let max_speed = tuning.asteroids.max_speed();
+    let max_rotation_speed = tuning.asteroids.max_rotation_speed();
     asteroid_count.0 = num_asteroids;
 
     for _ in 0..num_asteroids {
@@ -120,22 +159,19 @@ pub fn setup_asteroids(
         // Random direction as a unit vector
         let asteroid_direction_vec = Vec2::new(asteroid_direction.cos(), asteroid_direction.sin());
 
-        // distance along ray to screen edge
-        let screen_edge_distance = screen_edge_distance(&asteroid_direction_vec);
+        // distance along ray to the arena's edge
+        let edge_distance = arena.edge_distance(asteroid_direction_vec);
 
         // now I have a distance that represents the max length value
-        let random_length =
-            SAFE_RADIUS + ((rng.next_u32() as f32) % (screen_edge_distance - SAFE_RADIUS));
+        let random_length = safe_radius + ((rng.next_u32() as f32) % (edge_distance - safe_radius));
         let pos = asteroid_direction_vec * random_length;
 
-        let x_velo: f32 =
-            ((rng.next_u32() as f32) % (MAX_ASTEROID_SPEED * 2.)) - MAX_ASTEROID_SPEED;
-        let y_velo: f32 =
-            ((rng.next_u32() as f32) % (MAX_ASTEROID_SPEED * 2.)) - MAX_ASTEROID_SPEED;
+        let x_velo: f32 = ((rng.next_u32() as f32) % (max_speed * 2.)) - max_speed;
+        let y_velo: f32 = ((rng.next_u32() as f32) % (max_speed * 2.)) - max_speed;
 
         let heading: f32 = (rng.next_u32() as f32) % TAU;
-        let random_rotational_velo: f32 = ((rng.next_u32() as f32) % MAX_ASTEROID_ROTATION_SPEED)
-            - (0.5 * MAX_ASTEROID_ROTATION_SPEED);
+        let random_rotational_velo: f32 =
+            ((rng.next_u32() as f32) % max_rotation_speed) - (0.5 * max_rotation_speed);
 
         commands.spawn(AsteroidBundle::new(
             AsteroidSize::Large,
@@ -145,23 +181,28 @@ pub fn setup_asteroids(
             heading,
             &game_assets,
             &collision_hulls,
+            &hull_assets,
+            &tuning,
         ));
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn asteroid_destroyed_listener(
     mut commands: Commands,
     mut asteroid_ev: EventReader<AsteroidDestroyedEvent>,
-    mut rng: ResMut<GlobalEntropy<WyRand>>,
+    mut rng: ResMut<AsteroidSplitRng>,
     mut asteroid_count_query: Query<&mut AsteroidCount>,
     game_assets: Res<GameAssets>,
     collision_hulls: Res<CollisionHulls>,
+    hull_assets: Res<Assets<HullAsset>>,
+    tuning: Res<Tuning>,
 ) {
     for ev in asteroid_ev.read() {
         let (entity, transform, velocity, size) = (ev.0, ev.1, ev.2, ev.3);
         let mut asteroid_count = asteroid_count_query.single_mut();
 
-        _ = match size {
+        match size {
             AsteroidSize::Small => {
                 commands.entity(entity).insert(Despawning);
                 asteroid_count.0 -= 1;
@@ -176,6 +217,8 @@ pub fn asteroid_destroyed_listener(
                     0.0,
                     &game_assets,
                     &collision_hulls,
+                    &hull_assets,
+                    &tuning,
                 ));
                 commands.spawn(AsteroidBundle::new(
                     AsteroidSize::Small,
@@ -185,6 +228,8 @@ pub fn asteroid_destroyed_listener(
                     0.0,
                     &game_assets,
                     &collision_hulls,
+                    &hull_assets,
+                    &tuning,
                 ));
                 asteroid_count.0 += 1;
             }
@@ -198,6 +243,8 @@ pub fn asteroid_destroyed_listener(
                     0.0,
                     &game_assets,
                     &collision_hulls,
+                    &hull_assets,
+                    &tuning,
                 ));
                 commands.spawn(AsteroidBundle::new(
                     AsteroidSize::Medium,
@@ -207,6 +254,8 @@ pub fn asteroid_destroyed_listener(
                     0.0,
                     &game_assets,
                     &collision_hulls,
+                    &hull_assets,
+                    &tuning,
                 ));
                 asteroid_count.0 += 1;
             }
@@ -0,0 +1,182 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::Arena;
+use crate::Asteroid;
+use crate::AsteroidCount;
+use crate::Despawning;
+use crate::Invulnerable;
+use crate::Level;
+use crate::Player;
+use crate::PlayerKilledEvent;
+use crate::Projectile;
+use crate::Saucer;
+use crate::SaucerProjectile;
+use crate::Tuning;
+use crate::Velocity;
+
+/// What happens to an entity reaching the edge of the [`Arena`]. Only entities with one are held
+/// to the arena at all, anything else, like the camera, is free to be anywhere.
+///
+/// The game's own entities get the mode their kind has in the tuning's `boundaries` for the
+/// current level. An entity of some other kind keeps whatever mode it was given.
+#[derive(Component, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ArenaBoundary {
+    /// Leaves one side and comes back in at the other. What the game's entities are spawned with,
+    /// until they're given their kind's mode before they first reach an edge
+    #[default]
+    Wrap,
+    /// Bounces off the edges
+    Bounce,
+    /// Despawned once it's gone out of the arena entirely. The ship loses a life instead, or
+    /// wraps round while it's invulnerable
+    Kill,
+    /// Stopped by the edges, which destroy the ship unless it's invulnerable
+    Walls,
+}
+
+/// The [`ArenaBoundary`] of each kind of entity.
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Boundaries {
+    pub ship: ArenaBoundary,
+    pub projectiles: ArenaBoundary,
+    pub asteroids: ArenaBoundary,
+    pub saucers: ArenaBoundary,
+    pub saucer_projectiles: ArenaBoundary,
+}
+
+/// Gives the game's entities the boundary their kind has on the current level, so changing level
+/// or the tuning applies to everything already in the arena too.
+#[allow(clippy::type_complexity)]
+pub fn apply_boundary_tuning(
+    tuning: Res<Tuning>,
+    level_query: Query<&Level>,
+    mut query: Query<(
+        &mut ArenaBoundary,
+        Has<Player>,
+        Has<Projectile>,
+        Has<Asteroid>,
+        Has<Saucer>,
+        Has<SaucerProjectile>,
+    )>,
+) {
+    let level = level_query.get_single().map_or(1, |level| level.0);
+    let boundaries = tuning.boundaries.for_level(level);
+
+    for (mut boundary, player, projectile, asteroid, saucer, saucer_projectile) in query.iter_mut()
+    {
+        let mode = if player {
+            boundaries.ship
+        } else if projectile {
+            boundaries.projectiles
+        } else if asteroid {
+            boundaries.asteroids
+        } else if saucer {
+            boundaries.saucers
+        } else if saucer_projectile {
+            boundaries.saucer_projectiles
+        } else {
+            continue;
+        };
+        boundary.set_if_neq(mode);
+    }
+}
+
+/// Holds everything with an [`ArenaBoundary`] to the arena in its own way.
+#[allow(clippy::type_complexity)]
+pub fn arena_boundary_system(
+    mut commands: Commands,
+    arena: Res<Arena>,
+    mut query: Query<
+        (
+            Entity,
+            &ArenaBoundary,
+            &mut Transform,
+            Option<&mut Velocity>,
+            Option<&Sprite>,
+            Has<Player>,
+            Has<Invulnerable>,
+            Has<Asteroid>,
+        ),
+        (Without<Parent>, Without<Despawning>),
+    >,
+    mut asteroid_count_query: Query<&mut AsteroidCount>,
+    mut player_killed_event: EventWriter<PlayerKilledEvent>,
+) {
+    let half = arena.size / 2.;
+
+    for (entity, boundary, mut transform, mut velocity, sprite, player, invulnerable, asteroid) in
+        query.iter_mut()
+    {
+        let position = transform.translation.truncate();
+        // however it's turned nothing reaches further than half its longest side
+        let size = sprite.map_or(Vec2::ONE, |sprite| sprite.custom_size.unwrap_or(Vec2::ONE))
+            * transform.scale.truncate();
+        let reach = size.abs().max_element() / 2.;
+
+        match boundary {
+            ArenaBoundary::Wrap => {
+                // moved a whole arena across as soon as the centre leaves, so it carries on from
+                // the ghost drawn on the other side rather than popping off the screen
+                let wrapped = arena.wrap(position);
+                if wrapped != position {
+                    transform.translation = wrapped.extend(transform.translation.z);
+                }
+            }
+            ArenaBoundary::Kill => {
+                if (position.abs() - reach).cmpgt(half).any() {
+                    if player && invulnerable {
+                        // nothing else can destroy it yet, so neither can the edge
+                        let wrapped = arena.wrap(position);
+                        transform.translation = wrapped.extend(transform.translation.z);
+                        continue;
+                    }
+                    if player {
+                        // the ship goes the usual way, with a life lost and a respawn
+                        player_killed_event.send(PlayerKilledEvent);
+                        continue;
+                    }
+                    if asteroid {
+                        if let Ok(mut asteroid_count) = asteroid_count_query.get_single_mut() {
+                            asteroid_count.0 -= 1;
+                        }
+                    }
+                    commands.entity(entity).insert(Despawning);
+                }
+            }
+            ArenaBoundary::Bounce | ArenaBoundary::Walls => {
+                let bounce = *boundary == ArenaBoundary::Bounce;
+                let limit = (half - Vec2::splat(reach)).max(Vec2::ZERO);
+                let mut inside = position;
+                let mut touched = false;
+
+                for axis in 0..2 {
+                    let outward = if position[axis] > limit[axis] {
+                        1.
+                    } else if position[axis] < -limit[axis] {
+                        -1.
+                    } else {
+                        continue;
+                    };
+                    touched = true;
+                    inside[axis] = outward * limit[axis];
+
+                    // only turned back if it's still heading out, not already on its way in
+                    if let Some(velocity) = velocity.as_deref_mut() {
+                        if velocity.0[axis] * outward > 0. {
+                            velocity.0[axis] = if bounce { -velocity.0[axis] } else { 0. };
+                        }
+                    }
+                }
+
+                if touched {
+                    transform.translation = inside.extend(transform.translation.z);
+                    if !bounce && player && !invulnerable {
+                        player_killed_event.send(PlayerKilledEvent);
+                    }
+                }
+            }
+        }
+    }
+}
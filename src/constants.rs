@@ -1,12 +1,29 @@
 use bevy::prelude::*;
-pub const WINDOW_WIDTH: f32 = 1024.;
-pub const WINDOW_HEIGHT: f32 = 768.;
-pub const MIN_X_POSITION: f32 = 0. - (WINDOW_WIDTH / 2.);
-pub const MAX_X_POSITION: f32 = WINDOW_WIDTH / 2.;
-pub const MIN_Y_POSITION: f32 = 0. - (WINDOW_HEIGHT / 2.);
-pub const MAX_Y_POSITION: f32 = WINDOW_HEIGHT / 2.;
-
-pub const MAX_SPEED: f32 = WINDOW_WIDTH / SCREEN_CROSS_TIME;
-pub const MAX_SPEED_SQUARED: f32 = MAX_SPEED * MAX_SPEED;
+/// The size of the part of the arena the camera shows, in world units, however big the window is.
+/// The tuning file's "screens" are this size.
+pub const VIEW_WIDTH: f32 = 1024.;
+pub const VIEW_HEIGHT: f32 = 768.;
+pub const MIN_X_POSITION: f32 = 0. - (VIEW_WIDTH / 2.);
+pub const MAX_X_POSITION: f32 = VIEW_WIDTH / 2.;
+pub const MIN_Y_POSITION: f32 = 0. - (VIEW_HEIGHT / 2.);
+pub const MAX_Y_POSITION: f32 = VIEW_HEIGHT / 2.;
+
+/// Window sizes to pick from in the settings, the first is the size the window opens at
+pub const RESOLUTIONS: &[(u32, u32)] = &[(1024, 768), (1280, 960), (1600, 900), (1920, 1080)];
+
+/// Side of a collision broad phase cell, about the size of the largest asteroid
+pub const SPATIAL_HASH_CELL_SIZE: f32 = 128.;
+
+/// The most asteroids a level's field starts with, however far the tuning's count has grown
+pub const MAX_ASTEROID_COUNT: u32 = 64;
+
 pub const BACKGROUND_COLOR: Color = Color::BLACK;
-pub const SCREEN_CROSS_TIME: f32 = 1.5; // time in seconds to cross screen
+
+pub const SHIP_IMAGE: &str = "ship.png";
+pub const ASTEROID_LG_IMAGE: &str = "asteroid-lg.png";
+pub const ASTEROID_M_IMAGE: &str = "asteroid-m.png";
+pub const ASTEROID_SM_IMAGE: &str = "asteroid-sm.png";
+pub const SAUCER_LG_IMAGE: &str = "saucer-lg.png";
+pub const SAUCER_SM_IMAGE: &str = "saucer-sm.png";
+pub const FONT: &str = "LeagueMono-Thin.ttf";
+pub const TUNING_FILE: &str = "tuning.ron";
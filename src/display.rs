@@ -0,0 +1,145 @@
+use bevy::prelude::*;
+use bevy::render::camera::{ScalingMode, Viewport};
+use bevy::window::{PrimaryWindow, WindowMode};
+
+use crate::constants::*;
+use crate::{minimum_image, Arena, CameraScaling, Player, Settings, Tuning, Velocity};
+
+/// Fits the view of the arena to the window whatever its size and shape, and applies the display
+/// settings from the pause menu to the window. The game happens in world units, so a bigger
+/// window only draws it bigger. In an arena bigger than the view the camera follows the ship.
+pub struct DisplayPlugin;
+
+impl Plugin for DisplayPlugin {
+    fn build(&self, app: &mut App) {
+        // the letterbox bars, and whatever's beyond the arena when expanded
+        app.insert_resource(ClearColor(BACKGROUND_COLOR))
+            .add_systems(
+                Update,
+                (apply_window_settings, fit_camera_to_window, follow_ship).chain(),
+            );
+    }
+}
+
+/// Sets the window's mode and size when they're changed in the settings. Only what changed is
+/// applied, so toggling something else doesn't undo the window being resized by hand.
+fn apply_window_settings(
+    settings: Res<Settings>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+    mut applied: Local<Option<Settings>>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+    let Ok(mut window) = windows.get_single_mut() else {
+        return;
+    };
+    // the window opens as the default settings have it
+    let Some(previous) = applied.replace(settings.clone()) else {
+        return;
+    };
+
+    if previous.fullscreen != settings.fullscreen {
+        window.mode = if settings.fullscreen {
+            WindowMode::BorderlessFullscreen
+        } else {
+            WindowMode::Windowed
+        };
+    }
+    if previous.resolution != settings.resolution {
+        let (width, height) = RESOLUTIONS[settings.resolution];
+        window.resolution.set(width as f32, height as f32);
+    }
+}
+
+/// Scales the camera so the whole view fits the window, and the text and menus with it.
+fn fit_camera_to_window(
+    settings: Res<Settings>,
+    windows: Query<Ref<Window>, With<PrimaryWindow>>,
+    mut cameras: Query<(&mut Camera, &mut OrthographicProjection)>,
+    mut ui_scale: ResMut<UiScale>,
+) {
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+    if !window.is_changed() && !settings.is_changed() {
+        return;
+    }
+
+    let view = Vec2::new(VIEW_WIDTH, VIEW_HEIGHT);
+    let window_size = window.physical_size();
+    // physical pixels per world unit, nothing to fit while minimised
+    let scale = (window_size.as_vec2() / view).min_element();
+    if scale <= 0. {
+        return;
+    }
+
+    for (mut camera, mut projection) in cameras.iter_mut() {
+        match settings.camera_scaling {
+            CameraScaling::Letterbox => {
+                let size = (view * scale)
+                    .round()
+                    .as_uvec2()
+                    .clamp(UVec2::ONE, window_size);
+                camera.viewport = Some(Viewport {
+                    physical_position: (window_size - size) / 2,
+                    physical_size: size,
+                    ..default()
+                });
+                projection.scaling_mode = ScalingMode::Fixed {
+                    width: VIEW_WIDTH,
+                    height: VIEW_HEIGHT,
+                };
+            }
+            CameraScaling::Expand => {
+                camera.viewport = None;
+                projection.scaling_mode = ScalingMode::AutoMin {
+                    min_width: VIEW_WIDTH,
+                    min_height: VIEW_HEIGHT,
+                };
+            }
+        }
+    }
+
+    // laid out as if the window were the size of the view
+    ui_scale.0 = scale / window.scale_factor();
+}
+
+/// Keeps the camera on the ship, lagging a little behind and looking ahead the way it's going,
+/// along whichever ways the arena is bigger than the view. Along any other the camera stays in the
+/// middle like the arcade original. It wraps with the ship, and whatever's across the seam from it
+/// is drawn by the wrap ghosts.
+#[allow(clippy::type_complexity)]
+fn follow_ship(
+    time: Res<Time>,
+    arena: Res<Arena>,
+    tuning: Res<Tuning>,
+    player_query: Query<(&Transform, &Velocity), (With<Player>, Without<Camera2d>)>,
+    mut camera_query: Query<&mut Transform, With<Camera2d>>,
+) {
+    let Ok(mut camera) = camera_query.get_single_mut() else {
+        return;
+    };
+    let position = camera.translation.truncate();
+
+    // while the ship is waiting to respawn the camera stays where it was
+    let target = player_query
+        .get_single()
+        .map_or(position, |(transform, velocity)| {
+            transform.translation.truncate() + velocity.0 * tuning.world.camera_look_ahead
+        });
+    let scrolls = arena.size.cmpgt(Vec2::new(VIEW_WIDTH, VIEW_HEIGHT));
+    let target = Vec2::select(scrolls, target, Vec2::ZERO);
+
+    // the short way round, the ship may have just wrapped
+    let to_target = minimum_image(target - position, arena.size);
+    let lag = tuning.world.camera_lag;
+    let catch_up = if lag > 0. {
+        1. - (-time.delta_seconds() / lag).exp()
+    } else {
+        1.
+    };
+
+    let position = arena.wrap(position + to_target * catch_up);
+    camera.translation = position.extend(camera.translation.z);
+}
@@ -0,0 +1,139 @@
+use bevy::prelude::*;
+use bevy::transform::TransformSystem;
+
+use crate::Arena;
+use crate::ArenaBoundary;
+
+/// Draws a copy of anything that wraps hanging over an edge of the arena on the opposite side, so
+/// it slides off one edge and onto the other in one piece like the arcade original rather than
+/// popping across once its centre has left. When the camera looks across an edge of a bigger
+/// arena, everything near the far edge is drawn on this side of it too.
+///
+/// Ghosts are children of the sprite they copy, so they turn with it, blink with it while it's
+/// invulnerable and go when it's despawned. Nothing else ever sees them, it's only drawing.
+pub struct GhostPlugin;
+
+impl Plugin for GhostPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PostUpdate,
+            update_wrap_ghosts.before(TransformSystem::TransformPropagate),
+        );
+    }
+}
+
+/// A copy of its parent's sprite drawn `offset` away in the world, a whole arena across.
+#[derive(Component)]
+pub struct WrapGhost {
+    pub offset: Vec2,
+}
+
+/// Where the copies of a sprite at `position` reaching `radius` out go: a whole arena across in
+/// each direction, wherever they'd be in sight of the `visible` part of the world. With the camera
+/// showing just the arena that's across each edge the sprite hangs over, and across the corner as
+/// well when it's over two.
+pub fn ghost_offsets(position: Vec2, radius: f32, size: Vec2, visible: Rect) -> Vec<Vec2> {
+    let mut offsets = Vec::new();
+    for y in -1..=1 {
+        for x in -1..=1 {
+            let offset = Vec2::new(x as f32, y as f32) * size;
+            let copy = Rect::from_center_half_size(position + offset, Vec2::splat(radius));
+            if offset != Vec2::ZERO && !copy.intersect(visible).is_empty() {
+                offsets.push(offset);
+            }
+        }
+    }
+    offsets
+}
+
+#[allow(clippy::type_complexity)]
+fn update_wrap_ghosts(
+    mut commands: Commands,
+    arena: Res<Arena>,
+    parents: Query<
+        (
+            Entity,
+            &Transform,
+            &Sprite,
+            &Handle<Image>,
+            &ArenaBoundary,
+            Option<&Children>,
+        ),
+        (Without<Parent>, Without<WrapGhost>),
+    >,
+    mut ghosts: Query<(
+        &mut WrapGhost,
+        &mut Transform,
+        &mut Sprite,
+        &mut Handle<Image>,
+    )>,
+    cameras: Query<(&Transform, &OrthographicProjection), (With<Camera2d>, Without<WrapGhost>)>,
+) {
+    // without a camera only the arena itself is in sight
+    let visible = cameras.get_single().map_or(
+        Rect::from_center_size(Vec2::ZERO, arena.size),
+        |(transform, projection)| {
+            let centre = transform.translation.truncate();
+            Rect::from_corners(centre + projection.area.min, centre + projection.area.max)
+        },
+    );
+
+    for (entity, transform, sprite, texture, boundary, children) in parents.iter() {
+        // the sprite's whole diagonal, so it doesn't matter which way it's turned
+        let size = sprite.custom_size.unwrap_or(Vec2::ONE) * transform.scale.truncate();
+        let radius = size.length() / 2.;
+        // anything else is kept inside, a ghost left from before it stopped wrapping goes
+        let offsets = match boundary {
+            ArenaBoundary::Wrap => ghost_offsets(
+                transform.translation.truncate(),
+                radius,
+                arena.size,
+                visible,
+            ),
+            _ => Vec::new(),
+        };
+        let mut offsets = offsets.into_iter();
+
+        // reuse the ghosts it already has, they only need moving when it crosses a corner
+        for &child in children.into_iter().flatten() {
+            let Ok((mut ghost, mut ghost_transform, mut ghost_sprite, mut ghost_texture)) =
+                ghosts.get_mut(child)
+            else {
+                continue;
+            };
+            let Some(offset) = offsets.next() else {
+                commands.entity(child).despawn_recursive();
+                continue;
+            };
+
+            ghost.offset = offset;
+            *ghost_transform = ghost_transform_for(transform, offset);
+            *ghost_sprite = sprite.clone();
+            if *ghost_texture != *texture {
+                *ghost_texture = texture.clone();
+            }
+        }
+
+        for offset in offsets {
+            let ghost = commands
+                .spawn((
+                    WrapGhost { offset },
+                    SpriteBundle {
+                        sprite: sprite.clone(),
+                        texture: texture.clone(),
+                        transform: ghost_transform_for(transform, offset),
+                        ..default()
+                    },
+                ))
+                .id();
+            commands.entity(entity).add_child(ghost);
+        }
+    }
+}
+
+/// The local transform that puts a child `offset` away from `parent` in the world, undoing the
+/// parent's turn and scale so the ghost is drawn the same as the parent.
+fn ghost_transform_for(parent: &Transform, offset: Vec2) -> Transform {
+    let local = parent.rotation.inverse() * offset.extend(0.);
+    Transform::from_translation(local / parent.scale)
+}
@@ -0,0 +1,152 @@
+use std::path::Path;
+
+use bevy::{
+    asset::io::file::FileAssetReader,
+    prelude::*,
+    render::{
+        render_asset::RenderAssetUsages,
+        texture::{CompressedImageFormats, ImageSampler, ImageType},
+    },
+    time::TimeUpdateStrategy,
+};
+
+use crate::constants::*;
+use crate::{
+    projectile_hull, read_sidecar, trace_sprite_hull, CollisionHullOptions, CollisionHulls,
+    GameAssets, GameSeed, GameState, HullAsset, HullOptions, Level, Lives, Score,
+};
+
+/// Runs the game without a window or renderer, for use on top of `MinimalPlugins` in place of
+/// `LoadingPlugin` and `UiPlugin`.
+///
+/// Every app update advances the clock by exactly one fixed timestep, so each update runs one
+/// `FixedUpdate` tick. Menu screens are skipped straight back into the game and the session ends
+/// after `ticks` fixed updates or on game over, printing the final score, level, lives and seed.
+pub struct HeadlessPlugin {
+    pub ticks: u32,
+}
+
+#[derive(Resource)]
+pub struct HeadlessRun {
+    pub ticks_remaining: u32,
+}
+
+impl Plugin for HeadlessPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(
+            Time::<Fixed>::default().timestep(),
+        ))
+        .insert_resource(HeadlessRun {
+            ticks_remaining: self.ticks,
+        })
+        .insert_resource(GameAssets::default())
+        // there's no asset server to register hulls with, they're added by hand
+        .init_resource::<Assets<HullAsset>>()
+        // nothing presses keys without a window, but player_controls still reads them
+        .init_resource::<ButtonInput<KeyCode>>()
+        .add_systems(
+            Update,
+            start_processing.run_if(in_state(GameState::Loading)),
+        )
+        .add_systems(OnEnter(GameState::Processing), setup_headless_hulls)
+        .add_systems(
+            Update,
+            skip_menu
+                .run_if(in_state(GameState::NewGame).or_else(in_state(GameState::LevelComplete))),
+        )
+        .add_systems(FixedPostUpdate, count_ticks)
+        // before Last, where anything saved on exit sees the AppExit
+        .add_systems(PostUpdate, finish_session);
+    }
+}
+
+/// Decodes a PNG from the asset directory without going through the asset server.
+pub fn load_image_from_disk(path: &Path) -> Image {
+    let bytes = std::fs::read(path)
+        .unwrap_or_else(|err| panic!("failed to read {}: {}", path.display(), err));
+
+    Image::from_buffer(
+        &bytes,
+        ImageType::Extension("png"),
+        CompressedImageFormats::NONE,
+        true,
+        ImageSampler::Default,
+        RenderAssetUsages::default(),
+    )
+    .unwrap_or_else(|err| panic!("failed to decode {}: {}", path.display(), err))
+}
+
+fn start_processing(mut next_state: ResMut<NextState<GameState>>) {
+    next_state.set(GameState::Processing);
+}
+
+fn setup_headless_hulls(
+    mut commands: Commands,
+    options: Res<CollisionHullOptions>,
+    mut hull_assets: ResMut<Assets<HullAsset>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let asset_dir = FileAssetReader::get_base_path().join("assets");
+    let mut hull = |name: &str, image: &str, options: &HullOptions| {
+        hull_assets.add(load_or_trace_hull(name, &asset_dir.join(image), options))
+    };
+
+    let hulls = CollisionHulls {
+        ship: hull("ship", SHIP_IMAGE, &options.ship),
+        asteroid_lg: hull("large asteroid", ASTEROID_LG_IMAGE, &options.asteroid_lg),
+        asteroid_m: hull("medium asteroid", ASTEROID_M_IMAGE, &options.asteroid_m),
+        asteroid_sm: hull("small asteroid", ASTEROID_SM_IMAGE, &options.asteroid_sm),
+        saucer_lg: hull("large saucer", SAUCER_LG_IMAGE, &options.saucer_lg),
+        saucer_sm: hull("small saucer", SAUCER_SM_IMAGE, &options.saucer_sm),
+        projectile: hull_assets.add(projectile_hull()),
+    };
+
+    commands.insert_resource(hulls);
+    next_state.set(GameState::NewGame);
+}
+
+/// The sprite's hull from its sidecar while that's up to date, otherwise traced from the image.
+fn load_or_trace_hull(name: &str, image_path: &Path, options: &HullOptions) -> HullAsset {
+    let sidecar = std::fs::read(image_path)
+        .ok()
+        .and_then(|image_bytes| read_sidecar(image_path, &image_bytes).ok());
+
+    match sidecar {
+        Some(asset) if asset.options == *options => asset,
+        _ => trace_sprite_hull(name, &load_image_from_disk(image_path), options),
+    }
+}
+
+fn skip_menu(mut next_state: ResMut<NextState<GameState>>) {
+    next_state.set(GameState::InGame);
+}
+
+fn count_ticks(mut run: ResMut<HeadlessRun>) {
+    run.ticks_remaining = run.ticks_remaining.saturating_sub(1);
+}
+
+#[allow(clippy::too_many_arguments)]
+fn finish_session(
+    run: Res<HeadlessRun>,
+    state: Res<State<GameState>>,
+    score: Res<Score>,
+    seed: Res<GameSeed>,
+    level_query: Query<&Level>,
+    lives_query: Query<&Lives>,
+    mut exit: EventWriter<AppExit>,
+    mut finished: Local<bool>,
+) {
+    if *finished || (run.ticks_remaining > 0 && *state.get() != GameState::GameOver) {
+        return;
+    }
+
+    let level = level_query.single();
+    let lives = lives_query.single();
+    println!(
+        "score: {} level: {} lives: {} seed: {}",
+        score.0, level.0, lives.0, seed.0
+    );
+
+    *finished = true;
+    exit.send(AppExit::Success);
+}
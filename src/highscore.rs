@@ -0,0 +1,247 @@
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bevy::ecs::event::ManualEventReader;
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::prelude::*;
+
+use crate::{GameSeed, GameState, Level, Score};
+
+pub const HIGH_SCORE_COUNT: usize = 10;
+pub const INITIALS_LENGTH: usize = 3;
+
+/// Keeps the top [`HIGH_SCORE_COUNT`] scores in a file and runs the `EnterInitials` step when a
+/// finished game makes the table. Without a `path` the table only lives as long as the app.
+pub struct HighScorePlugin {
+    pub path: Option<PathBuf>,
+}
+
+impl Default for HighScorePlugin {
+    fn default() -> Self {
+        HighScorePlugin {
+            path: dirs::data_dir().map(|dir| dir.join("stroids").join("highscores.txt")),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HighScore {
+    pub initials: String,
+    pub score: u64,
+    pub level: u32,
+    /// `YYYY-MM-DD`
+    pub date: String,
+    pub seed: u64,
+}
+
+#[derive(Resource, Clone, Debug, Default)]
+pub struct HighScores {
+    pub path: Option<PathBuf>,
+    /// Highest first
+    pub entries: Vec<HighScore>,
+}
+
+/// The initials typed in so far on the `EnterInitials` screen.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct PendingInitials(pub String);
+
+impl HighScores {
+    /// Reads the table, one tab separated `initials score level date seed` line per entry. A
+    /// missing file is an empty table.
+    pub fn load(path: &Path) -> std::io::Result<HighScores> {
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(err),
+        };
+
+        let mut entries = Vec::new();
+        for (line_number, line) in contents.lines().enumerate() {
+            match parse_entry(line) {
+                Some(entry) => entries.push(entry),
+                None => warn!(
+                    "skipping bad high score on line {} of {}",
+                    line_number + 1,
+                    path.display()
+                ),
+            }
+        }
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.score));
+        entries.truncate(HIGH_SCORE_COUNT);
+
+        Ok(HighScores {
+            path: Some(path.to_path_buf()),
+            entries,
+        })
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+
+        let contents: String = self
+            .entries
+            .iter()
+            .map(|entry| {
+                format!(
+                    "{}\t{}\t{}\t{}\t{}\n",
+                    entry.initials, entry.score, entry.level, entry.date, entry.seed
+                )
+            })
+            .collect();
+
+        std::fs::write(path, contents)
+    }
+
+    pub fn qualifies(&self, score: u64) -> bool {
+        score > 0
+            && (self.entries.len() < HIGH_SCORE_COUNT
+                || self.entries.iter().any(|entry| score > entry.score))
+    }
+
+    /// Adds an entry in order, ties go below the scores already on the table.
+    pub fn insert(&mut self, entry: HighScore) {
+        let position = self
+            .entries
+            .iter()
+            .position(|existing| entry.score > existing.score)
+            .unwrap_or(self.entries.len());
+
+        self.entries.insert(position, entry);
+        self.entries.truncate(HIGH_SCORE_COUNT);
+    }
+}
+
+fn parse_entry(line: &str) -> Option<HighScore> {
+    let mut fields = line.split('\t');
+
+    let entry = HighScore {
+        initials: fields.next()?.to_string(),
+        score: fields.next()?.parse().ok()?,
+        level: fields.next()?.parse().ok()?,
+        date: fields.next()?.to_string(),
+        seed: fields.next()?.parse().ok()?,
+    };
+
+    fields.next().is_none().then_some(entry)
+}
+
+/// Today's UTC date as `YYYY-MM-DD`.
+fn today() -> String {
+    let days = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs() / 86_400)
+        .unwrap_or(0) as i64;
+
+    // days since the epoch to a civil date, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+impl Plugin for HighScorePlugin {
+    fn build(&self, app: &mut App) {
+        let high_scores = match &self.path {
+            Some(path) => HighScores::load(path).unwrap_or_else(|err| {
+                warn!(
+                    "failed to read high scores from {}: {}",
+                    path.display(),
+                    err
+                );
+                HighScores {
+                    path: Some(path.clone()),
+                    entries: Vec::new(),
+                }
+            }),
+            None => HighScores::default(),
+        };
+
+        app.insert_resource(high_scores)
+            .init_resource::<PendingInitials>()
+            .init_resource::<InitialsKeys>()
+            .add_systems(OnEnter(GameState::EnterInitials), reset_initials)
+            .add_systems(
+                Update,
+                enter_initials.run_if(in_state(GameState::EnterInitials)),
+            );
+    }
+}
+
+/// Where `enter_initials` is up to in the key presses, kept apart from every other reader's so
+/// the entry can skip the ones from before it started.
+#[derive(Resource, Default)]
+pub struct InitialsKeys(ManualEventReader<KeyboardInput>);
+
+/// Starts the entry empty, skipping the key presses so far so the P or Enter that left the game
+/// over screen isn't read as the first initial or as accepting the entry.
+fn reset_initials(
+    mut initials: ResMut<PendingInitials>,
+    mut keys: ResMut<InitialsKeys>,
+    keyboard_events: Res<Events<KeyboardInput>>,
+) {
+    initials.0.clear();
+    keys.0.clear(&keyboard_events);
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn enter_initials(
+    mut keys: ResMut<InitialsKeys>,
+    keyboard_events: Res<Events<KeyboardInput>>,
+    mut initials: ResMut<PendingInitials>,
+    mut high_scores: ResMut<HighScores>,
+    score: Res<Score>,
+    seed: Res<GameSeed>,
+    level_query: Query<&Level>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    for event in keys.0.read(&keyboard_events) {
+        if !event.state.is_pressed() {
+            continue;
+        }
+
+        match &event.logical_key {
+            Key::Character(text) => {
+                for c in text.chars().filter(char::is_ascii_alphabetic) {
+                    if initials.0.len() < INITIALS_LENGTH {
+                        initials.0.push(c.to_ascii_uppercase());
+                    }
+                }
+            }
+            Key::Backspace => {
+                initials.0.pop();
+            }
+            // a high score always gets some initials
+            Key::Enter if !initials.0.is_empty() => {
+                high_scores.insert(HighScore {
+                    initials: initials.0.clone(),
+                    score: score.0,
+                    level: level_query.single().0,
+                    date: today(),
+                    seed: seed.0,
+                });
+
+                if let Err(err) = high_scores.save() {
+                    error!("failed to save high scores: {}", err);
+                }
+
+                next_state.set(GameState::NewGame);
+                return;
+            }
+            _ => {}
+        }
+    }
+}
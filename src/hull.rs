@@ -1,81 +1,486 @@
+use std::{fmt, ops::Range};
+
 use bevy::{prelude::*, render::render_resource::TextureFormat};
+use serde::{Deserialize, Serialize};
 
-#[derive(Component, Clone, Default)]
+/// A collision shape made of one or more convex pieces, each a closed counter-clockwise path.
+/// Concave sprites take several pieces to follow their outline, everything else takes one.
+#[derive(Clone, Debug, Default)]
 pub struct Hull {
-    path: Vec<Vec2>,
+    pieces: Vec<Vec<Vec2>>,
+    /// Distance from the centre to the furthest vertex
+    radius: f32,
+}
+
+/// How a hull is traced from a sprite's pixels.
+///
+/// A hull around every opaque pixel can have dozens of nearly lined up corners, each one another
+/// axis for every collision test. Simplifying trims corners by pushing edges outwards until the
+/// neighbouring ones meet, so the hull only ever grows and still holds the whole sprite.
+///
+/// A concave hull instead follows the outline of the sprite into its dents, then splits it into
+/// convex pieces. Its corners are only trimmed where cutting across them grows the outline by at
+/// most `max_error`, so it never cuts into the sprite, and a sprite that can't be brought down to
+/// `max_vertices` corners that way gets the convex hull instead.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct HullOptions {
+    /// Pixels more opaque than this, 0 to 1, are part of the sprite
+    pub alpha_threshold: f32,
+    /// Corners to keep at most, however far the hull has to grow past the sprite. Never below 3.
+    /// For a concave hull, the corners of the whole outline rather than of each piece, and never
+    /// at the cost of growing past `max_error`
+    pub max_vertices: Option<usize>,
+    /// Pixels the hull may grow past the sprite while trimming corners beyond `max_vertices`
+    pub max_error: f32,
+    /// Follow the outline of the sprite instead of wrapping every visible pixel in one convex
+    /// hull. Falls back to the convex hull when the sprite is more than one shape, the outline
+    /// needs more than `max_vertices` corners or it can't be split
+    pub concave: bool,
+}
+
+impl Default for HullOptions {
+    fn default() -> Self {
+        HullOptions {
+            alpha_threshold: 0.,
+            max_vertices: None,
+            max_error: 0.,
+            concave: false,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum HullError {
+    /// The pixel format isn't one hulls can be traced from
+    UnsupportedFormat(TextureFormat),
+    /// There are fewer bytes than the image's size and format need
+    MissingPixelData { expected: usize, found: usize },
+    /// No pixel is over the alpha threshold
+    Transparent,
+    /// The visible pixels lie on a single line or point, so there's no area to collide with
+    Degenerate,
+}
+
+impl fmt::Display for HullError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HullError::UnsupportedFormat(format) => {
+                write!(f, "can't trace a hull from {:?} pixels", format)
+            }
+            HullError::MissingPixelData { expected, found } => {
+                write!(f, "expected {} bytes of pixels, found {}", expected, found)
+            }
+            HullError::Transparent => write!(f, "no pixel is over the alpha threshold"),
+            HullError::Degenerate => write!(f, "the visible pixels have no area"),
+        }
+    }
 }
 
-#[derive(Debug, PartialEq, Eq, Copy, Clone)]
-pub struct HullCollision;
+impl std::error::Error for HullError {}
+
+/// A `Hull` placed in the world by its entity's transform. `update_world_hulls` refreshes it once
+/// per `FixedUpdate` tick so every collision test that tick works on the same vertices and
+/// normals instead of transforming the hull again for each pair.
+#[derive(Component, Clone, Default)]
+pub struct WorldHull {
+    /// Every piece's vertices one after the other, and the edge normals likewise
+    vertices: Vec<Vec2>,
+    normals: Vec<Vec2>,
+    /// Where each piece's vertices and normals are in the shared buffers
+    pieces: Vec<(Range<usize>, Range<usize>)>,
+    centre: Vec2,
+    radius: f32,
+    /// Kept inside the arena rather than wrapping around its edges
+    bounded: bool,
+}
+
+/// Where and how deeply two hulls overlap.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct HullCollision {
+    /// Unit axis of least overlap, pointing from the first hull towards the second
+    pub normal: Vec2,
+    /// Overlap along `normal`, moving the second hull `normal * depth` separates the two
+    pub depth: f32,
+    /// Roughly where the hulls touch, halfway between the deepest points of each inside the other
+    pub point: Vec2,
+}
 
 #[derive(Clone, Copy, Debug)]
-struct Projection(f32, f32);
+pub struct Projection(f32, f32);
 impl Projection {
     fn overlaps(&self, other: &Projection) -> bool {
         self.0 <= other.1 && self.1 >= other.0
     }
+
+    fn shifted(&self, by: f32) -> Projection {
+        Projection(self.0 + by, self.1 + by)
+    }
+
+    /// How far `other` has to move along the axis to stop overlapping, negative to move it
+    /// backwards.
+    fn push_out(&self, other: &Projection) -> f32 {
+        let forwards = self.1 - other.0;
+        let backwards = other.1 - self.0;
+        if forwards < backwards {
+            forwards
+        } else {
+            -backwards
+        }
+    }
 }
 
 impl Hull {
-    pub fn new(image: &Image) -> Hull {
-        let image_vec = extract_visible_pixels(&image);
-
-        convex_hull(&image_vec)
-    }
-
-    pub fn draw_as_lines(
-        self: &Self,
-        gizmos: &mut Gizmos,
-        color: Color,
-        position: &Vec2,
-        rotation: &Quat,
-    ) {
-        let Hull { path: pts } = self;
-        for line in pts.windows(2) {
-            let start = (*rotation * line[0].extend(0.0)).truncate() + *position;
-            let end = (*rotation * line[1].extend(0.0)).truncate() + *position;
+    /// The hull of the image's visible pixels, normalised so the whole image spans one unit each
+    /// way. `sized` scales it to the size the sprite is drawn at.
+    pub fn new(image: &Image, options: &HullOptions) -> Result<Hull, HullError> {
+        let mask = visible_mask(image, options.alpha_threshold)?;
+        if !mask.contains(&true) {
+            return Err(HullError::Transparent);
+        }
+
+        let size = image.size();
+        let (width, height) = (size.x as usize, size.y as usize);
+
+        let concave = if options.concave {
+            concave_pieces(&mask, width, height, options)
+        } else {
+            None
+        };
+
+        let hull = match concave {
+            Some(pieces) => Hull::from_pieces(pieces),
+            None => {
+                let hull = convex_hull(&visible_pixels(&mask, width, height));
+                // the path is closed, so a triangle is four points
+                if hull.pieces[0].len() < 4 {
+                    return Err(HullError::Degenerate);
+                }
+
+                Hull::from_path(simplify(
+                    &hull.pieces[0],
+                    options.max_vertices,
+                    options.max_error,
+                ))
+            }
+        };
+
+        Ok(hull.sized(image.size_f32().recip()))
+    }
+
+    /// This hull stretched by `size` along each axis, from a normalised hull to one for a sprite
+    /// drawn `size` big.
+    pub fn sized(&self, size: Vec2) -> Hull {
+        Hull::from_pieces(
+            self.pieces
+                .iter()
+                .map(|piece| piece.iter().map(|v| *v * size).collect())
+                .collect(),
+        )
+    }
+
+    /// The convex pieces, each a closed path of corners in order, the last repeating the first.
+    pub fn pieces(&self) -> &[Vec<Vec2>] {
+        &self.pieces
+    }
+
+    /// The smallest rectangle around the centre holding the hull, unrotated.
+    pub fn bounds(&self) -> Rect {
+        self.pieces
+            .iter()
+            .flatten()
+            .fold(Rect::new(0., 0., 0., 0.), |bounds, v| {
+                bounds.union_point(*v)
+            })
+    }
+
+    fn from_path(path: Vec<Vec2>) -> Hull {
+        Hull::from_pieces(vec![path])
+    }
+
+    /// A hull of closed counter-clockwise convex paths.
+    pub fn from_pieces(pieces: Vec<Vec<Vec2>>) -> Hull {
+        let radius = pieces
+            .iter()
+            .flatten()
+            .map(|v| v.length())
+            .fold(0., f32::max);
+
+        Hull { pieces, radius }
+    }
+
+    /// The radius of a circle around the centre that holds the whole hull, whatever its rotation.
+    pub fn radius(&self) -> f32 {
+        self.radius
+    }
+
+    pub fn area(&self) -> f32 {
+        self.edges().map(|(a, b)| a.perp_dot(b)).sum::<f32>().abs() / 2.
+    }
+
+    /// Moment of inertia about the centre of a solid plate of this shape weighing `mass`.
+    pub fn moment_of_inertia(&self, mass: f32) -> f32 {
+        let (numerator, denominator) = self.edges().fold((0., 0.), |(num, den), (a, b)| {
+            // signed, so pieces away from the centre subtract the triangle they don't cover
+            let cross = a.perp_dot(b);
+            (num + cross * (a.dot(a) + a.dot(b) + b.dot(b)), den + cross)
+        });
+
+        if denominator == 0. {
+            0.
+        } else {
+            mass * numerator / (6. * denominator)
+        }
+    }
+
+    /// Every side of every piece, including the one closing it whether or not the path repeats
+    /// its first vertex. The pieces don't overlap, so sums over them add up to the whole hull.
+    fn edges(&self) -> impl Iterator<Item = (Vec2, Vec2)> + '_ {
+        self.pieces.iter().flat_map(|piece| {
+            piece
+                .iter()
+                .zip(piece.iter().cycle().skip(1))
+                .map(|(a, b)| (*a, *b))
+        })
+    }
+
+    pub fn draw_as_lines(&self, gizmos: &mut Gizmos, color: Color, transform: &Transform) {
+        for line in self.pieces.iter().flat_map(|piece| piece.windows(2)) {
+            let start = transform.transform_point(line[0].extend(0.0)).truncate();
+            let end = transform.transform_point(line[1].extend(0.0)).truncate();
             gizmos.line_2d(start, end, color);
         }
     }
 
     pub fn from_bb(tr: Vec2, tl: Vec2, br: Vec2, bl: Vec2) -> Hull {
-        let mut path: Vec<Vec2> = Vec::new();
+        let path: Vec<Vec2> = vec![tr, tl, bl, br];
+
+        Hull::from_path(path)
+    }
 
-        path.push(tr);
-        path.push(tl);
-        path.push(bl);
-        path.push(br);
+    /// The whole of a normalised sprite, a square one unit across.
+    pub fn unit_square() -> Hull {
+        Hull::from_bb(
+            Vec2::new(0.5, 0.5),
+            Vec2::new(-0.5, 0.5),
+            Vec2::new(0.5, -0.5),
+            Vec2::new(-0.5, -0.5),
+        )
+    }
+
+    /// A regular polygon with its corners on a circle, for testing an area rather than a sprite.
+    pub fn circle(radius: f32, segments: usize) -> Hull {
+        let mut path: Vec<Vec2> = (0..segments)
+            .map(|i| Vec2::from_angle(std::f32::consts::TAU * i as f32 / segments as f32) * radius)
+            .collect();
+        path.push(path[0]);
 
-        Hull { path }
+        Hull::from_path(path)
     }
 }
 
+impl WorldHull {
+    pub fn new(hull: &Hull, transform: &Transform) -> WorldHull {
+        let mut world_hull = WorldHull::default();
+        world_hull.update(hull, transform);
+        world_hull
+    }
+
+    /// Moves the hull to the transform, reusing the vertex and normal buffers.
+    pub fn update(&mut self, hull: &Hull, transform: &Transform) {
+        self.vertices.clear();
+        self.normals.clear();
+        self.pieces.clear();
+
+        for piece in &hull.pieces {
+            let vertices_start = self.vertices.len();
+            let normals_start = self.normals.len();
+
+            // vertices need to be transformed by their scale, rotation and position
+            self.vertices.extend(
+                piece
+                    .iter()
+                    .map(|v| transform.transform_point(v.extend(0.0)).truncate()),
+            );
+
+            let vertices = vertices_start..self.vertices.len();
+            self.normals.extend(
+                self.vertices[vertices.clone()]
+                    .windows(2)
+                    .map(|edge| normalized_normal(edge[0], edge[1])),
+            );
+
+            self.pieces
+                .push((vertices, normals_start..self.normals.len()));
+        }
+
+        self.centre = transform.translation.truncate();
+        self.radius = hull.radius * transform.scale.truncate().abs().max_element();
+    }
+
+    /// Slides the hull along without turning it, as when an entity is pushed out of an overlap.
+    pub fn translate(&mut self, offset: Vec2) {
+        for vertex in &mut self.vertices {
+            *vertex += offset;
+        }
+        self.centre += offset;
+    }
+
+    /// Each piece's vertices and edge normals.
+    pub fn pieces(&self) -> impl Iterator<Item = (&[Vec2], &[Vec2])> {
+        self.pieces.iter().map(|(vertices, normals)| {
+            (
+                &self.vertices[vertices.clone()],
+                &self.normals[normals.clone()],
+            )
+        })
+    }
+
+    /// Not placed yet, as for an entity spawned since `update_world_hulls` last ran.
+    pub fn is_empty(&self) -> bool {
+        self.pieces.is_empty()
+    }
+
+    pub fn centre(&self) -> Vec2 {
+        self.centre
+    }
+
+    /// Marks the hull as kept inside the arena, so it's never tested across the edges.
+    pub fn set_bounded(&mut self, bounded: bool) {
+        self.bounded = bounded;
+    }
+
+    pub fn radius(&self) -> f32 {
+        self.radius
+    }
+}
+
+/// Tests hulls that aren't kept up to date on an entity, transforming both on the spot.
 pub fn check_for_collision(
     hull_a: &Hull,
     hull_a_transform: &Transform,
     hull_b: &Hull,
     hull_b_transform: &Transform,
 ) -> Option<HullCollision> {
-    let hull_a = hull_at_orientation(&hull_a, &hull_a_transform);
-    let hull_b = hull_at_orientation(&hull_b, &hull_b_transform);
+    check_world_hull_collision(
+        &WorldHull::new(hull_a, hull_a_transform),
+        &WorldHull::new(hull_b, hull_b_transform),
+    )
+}
 
-    let hull_a_axes = get_all_normals(&hull_a)
-        .into_iter()
-        .chain(get_all_normals(&hull_b));
+/// Separating axis test between two hulls already in world space, piece by piece. When several
+/// pairs of pieces overlap, the deepest one is the collision.
+pub fn check_world_hull_collision(hull_a: &WorldHull, hull_b: &WorldHull) -> Option<HullCollision> {
+    check_offset_collision(hull_a, hull_b, Vec2::ZERO)
+}
 
-    for axis in hull_a_axes {
-        let proj_a = project(&hull_a, &axis);
-        let proj_b = project(&hull_b, &axis);
+/// [`check_world_hull_collision`] in an arena `size` big and centred on the origin, whose edges
+/// wrap around so leaving one side comes back in at the other. The second hull is tested at
+/// whichever of its wrapped positions is nearest the first, so a hull hanging off one edge
+/// collides with anything touching the part of it drawn at the opposite edge. The collision point
+/// is on the first hull's side of the seam. A hull that's bounded only collides where it is.
+pub fn check_wrapped_collision(
+    hull_a: &WorldHull,
+    hull_b: &WorldHull,
+    size: Vec2,
+) -> Option<HullCollision> {
+    if hull_a.bounded || hull_b.bounded {
+        return check_world_hull_collision(hull_a, hull_b);
+    }
+    let apart = hull_b.centre - hull_a.centre;
+    check_offset_collision(hull_a, hull_b, minimum_image(apart, size) - apart)
+}
+
+/// The shortest way across an arena `size` big with wrapping edges to cover `delta`, which is
+/// `delta` itself unless going the other way round over an edge is shorter.
+pub fn minimum_image(delta: Vec2, size: Vec2) -> Vec2 {
+    delta - size * (delta / size).round()
+}
+
+/// Tests the hulls with the second one moved by `offset`.
+fn check_offset_collision(
+    hull_a: &WorldHull,
+    hull_b: &WorldHull,
+    offset: Vec2,
+) -> Option<HullCollision> {
+    // hulls whose bounding circles don't touch can't overlap, skip the SAT test
+    let reach = hull_a.radius + hull_b.radius;
+    if hull_a.centre.distance_squared(hull_b.centre + offset) > reach * reach {
+        return None;
+    }
+
+    hull_a
+        .pieces()
+        .flat_map(|piece_a| hull_b.pieces().map(move |piece_b| (piece_a, piece_b)))
+        .filter_map(|((vertices_a, normals_a), (vertices_b, normals_b))| {
+            check_piece_collision(vertices_a, normals_a, vertices_b, normals_b, offset)
+        })
+        .max_by(|a, b| a.depth.total_cmp(&b.depth))
+}
+
+/// Separating axis test between two convex pieces, the second moved by `offset`.
+fn check_piece_collision(
+    vertices_a: &[Vec2],
+    normals_a: &[Vec2],
+    vertices_b: &[Vec2],
+    normals_b: &[Vec2],
+    offset: Vec2,
+) -> Option<HullCollision> {
+    let mut normal = Vec2::ZERO;
+    let mut depth = f32::MAX;
+
+    for axis in normals_a.iter().chain(normals_b.iter()) {
+        let proj_a = project(vertices_a, axis);
+        let proj_b = project(vertices_b, axis).shifted(offset.dot(*axis));
 
         if !proj_a.overlaps(&proj_b) {
             return None;
         }
+
+        // the minimum translation vector is along the axis with the least overlap
+        let push = proj_a.push_out(&proj_b);
+        if push.abs() < depth {
+            depth = push.abs();
+            normal = *axis * push.signum();
+        }
     }
 
-    Some(HullCollision)
+    let point = (support(vertices_a, normal) + support(vertices_b, -normal) + offset) / 2.;
+
+    Some(HullCollision {
+        normal,
+        depth,
+        point,
+    })
 }
 
-pub fn project(shape: &Vec<Vec2>, axis: &Vec2) -> Projection {
+/// The vertex furthest along `direction`, or the middle of the edge when a flat side faces that
+/// way.
+fn support(vertices: &[Vec2], direction: Vec2) -> Vec2 {
+    // closed paths repeat the first vertex at the end, don't count it twice
+    let vertices = match vertices {
+        [first, .., last] if first == last => &vertices[..vertices.len() - 1],
+        _ => vertices,
+    };
+
+    let furthest = vertices
+        .iter()
+        .map(|v| v.dot(direction))
+        .fold(f32::MIN, f32::max);
+
+    // within half a pixel counts as level with the furthest vertex
+    let (sum, count) = vertices
+        .iter()
+        .filter(|v| furthest - v.dot(direction) < 0.5)
+        .fold((Vec2::ZERO, 0), |(sum, count), v| (sum + *v, count + 1));
+
+    if count == 0 {
+        Vec2::ZERO
+    } else {
+        sum / count as f32
+    }
+}
+
+pub fn project(shape: &[Vec2], axis: &Vec2) -> Projection {
     let mut max_p = f32::MIN;
     let mut min_p = f32::MAX;
 
@@ -86,23 +491,7 @@ pub fn project(shape: &Vec<Vec2>, axis: &Vec2) -> Projection {
         max_p = max_p.max(p);
     }
 
-    return Projection(min_p, max_p);
-}
-
-pub fn hull_at_orientation(hull: &Hull, transform: &Transform) -> Vec<Vec2> {
-    let Hull { path: raw_vertices } = hull;
-
-    // vertices need to be transformed by their rotation and position
-    let mut vertices = Vec::<Vec2>::new();
-    let translation = &transform.translation;
-    let rotation = &transform.rotation;
-
-    for v in raw_vertices {
-        let v_oriented = (*rotation * v.extend(0.0)) + *translation;
-        vertices.push(v_oriented.truncate());
-    }
-
-    vertices
+    Projection(min_p, max_p)
 }
 
 pub fn normalized_normal(v1: Vec2, v2: Vec2) -> Vec2 {
@@ -110,14 +499,6 @@ pub fn normalized_normal(v1: Vec2, v2: Vec2) -> Vec2 {
     edge.perp().normalize()
 }
 
-pub fn get_all_normals(pts: &Vec<Vec2>) -> Vec<Vec2> {
-    let mut normals = Vec::<Vec2>::new();
-    for edge in pts.windows(2) {
-        normals.push(normalized_normal(edge[0], edge[1]))
-    }
-    normals
-}
-
 #[derive(Eq, PartialEq)]
 enum Orientation {
     Collinear,
@@ -137,7 +518,7 @@ fn orientation(a: Vec2, b: Vec2, c: Vec2) -> Orientation {
     }
 }
 
-fn convex_hull(pixel_data: &Vec<Vec2>) -> Hull {
+fn convex_hull(pixel_data: &[Vec2]) -> Hull {
     let mut origin: Vec2 = Vec2::MAX;
 
     // scan and find the minimum origin
@@ -193,57 +574,469 @@ fn convex_hull(pixel_data: &Vec<Vec2>) -> Hull {
         hull.push(pt);
     }
 
-    hull.push(*hull.first().clone().unwrap());
+    hull.push(*hull.first().unwrap());
+
+    Hull::from_path(hull)
+}
+
+/// Trims corners off a closed counter-clockwise convex path. An edge is removed by extending the
+/// edges either side of it until they meet, the error being how far that point sticks out past
+/// the removed edge. The cheapest edge goes first, while there are more than `max_vertices`
+/// corners or the error is within `max_error`.
+fn simplify(path: &[Vec2], max_vertices: Option<usize>, max_error: f32) -> Vec<Vec2> {
+    let mut corners = path[..path.len() - 1].to_vec();
+    let max_vertices = max_vertices.unwrap_or(usize::MAX).max(3);
+
+    while corners.len() > 3 {
+        let count = corners.len();
+        let cheapest = (0..count)
+            .filter_map(|i| {
+                let before = corners[(i + count - 1) % count];
+                let start = corners[i];
+                let end = corners[(i + 1) % count];
+                let after = corners[(i + 2) % count];
+                edge_collapse(before, start, end, after).map(|(point, error)| (i, point, error))
+            })
+            .min_by(|a, b| a.2.total_cmp(&b.2));
+
+        let Some((i, point, error)) = cheapest else {
+            break;
+        };
+        if count <= max_vertices && error > max_error {
+            break;
+        }
+
+        // the edge from corners[i] to corners[i + 1] becomes the single corner where its
+        // neighbours meet
+        corners[i] = point;
+        corners.remove((i + 1) % count);
+    }
 
-    Hull { path: hull }
+    corners.push(corners[0]);
+    corners
+}
+
+/// Where the edges either side of `start`..`end` meet when extended past it, and how far that is
+/// from the edge. `None` when they turn away from each other and never meet on the outside.
+fn edge_collapse(before: Vec2, start: Vec2, end: Vec2, after: Vec2) -> Option<(Vec2, f32)> {
+    let incoming = start - before;
+    let outgoing = after - end;
+    let edge = end - start;
+
+    let turn = incoming.perp_dot(outgoing);
+    if turn <= f32::EPSILON || edge.length_squared() <= f32::EPSILON {
+        return None;
+    }
+
+    let along = edge.perp_dot(outgoing) / turn;
+    let point = start + incoming * along;
+    let error = edge.perp_dot(point - start).abs() / edge.length();
+
+    Some((point, error))
 }
 
 fn angle_from_vec(x: f32, y: f32) -> f32 {
     y.atan2(x)
 }
 
-fn extract_visible_pixels(image: &Image) -> Vec<Vec2> {
-    let mut visible_points = Vec::new();
+/// Reads how opaque a pixel is, 0 to 1, from its bytes.
+type AlphaReader = fn(&[u8]) -> f32;
+
+/// Bytes per pixel and where to find the alpha in them, for the formats hulls can be traced from.
+fn alpha_reader(format: TextureFormat) -> Option<(usize, AlphaReader)> {
+    let reader: (usize, AlphaReader) = match format {
+        TextureFormat::Rgba8Unorm
+        | TextureFormat::Rgba8UnormSrgb
+        | TextureFormat::Bgra8Unorm
+        | TextureFormat::Bgra8UnormSrgb => (4, |pixel| unorm8(pixel[3])),
+        // single channel images have no alpha, the channel itself is the mask
+        TextureFormat::R8Unorm => (1, |pixel| unorm8(pixel[0])),
+        // grey and alpha, as greyscale PNGs with transparency load
+        TextureFormat::Rg8Unorm => (2, |pixel| unorm8(pixel[1])),
+        TextureFormat::R16Unorm => (2, |pixel| unorm16(&pixel[0..2])),
+        TextureFormat::Rg16Unorm => (4, |pixel| unorm16(&pixel[2..4])),
+        TextureFormat::Rgba16Unorm => (8, |pixel| unorm16(&pixel[6..8])),
+        TextureFormat::R16Float => (2, |pixel| float16(&pixel[0..2])),
+        TextureFormat::Rg16Float => (4, |pixel| float16(&pixel[2..4])),
+        TextureFormat::Rgba16Float => (8, |pixel| float16(&pixel[6..8])),
+        TextureFormat::R32Float => (4, |pixel| float32(&pixel[0..4])),
+        TextureFormat::Rg32Float => (8, |pixel| float32(&pixel[4..8])),
+        TextureFormat::Rgba32Float => (16, |pixel| float32(&pixel[12..16])),
+        _ => return None,
+    };
+
+    Some(reader)
+}
+
+fn unorm8(value: u8) -> f32 {
+    value as f32 / u8::MAX as f32
+}
+
+fn unorm16(bytes: &[u8]) -> f32 {
+    u16::from_le_bytes([bytes[0], bytes[1]]) as f32 / u16::MAX as f32
+}
+
+fn float16(bytes: &[u8]) -> f32 {
+    let bits = u16::from_le_bytes([bytes[0], bytes[1]]);
+    let sign = if bits & 0x8000 == 0 { 1. } else { -1. };
+    let exponent = ((bits >> 10) & 0x1f) as i32;
+    let mantissa = (bits & 0x3ff) as f32;
+
+    let magnitude = match exponent {
+        0 => mantissa * 2f32.powi(-24),
+        0x1f if mantissa == 0. => f32::INFINITY,
+        0x1f => f32::NAN,
+        _ => (1. + mantissa / 1024.) * 2f32.powi(exponent - 15),
+    };
+
+    sign * magnitude
+}
+
+fn float32(bytes: &[u8]) -> f32 {
+    f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
 
+/// Which pixels are more opaque than the threshold, row by row from the top left.
+fn visible_mask(image: &Image, alpha_threshold: f32) -> Result<Vec<bool>, HullError> {
     let pixel_data = &image.data;
     let width = image.texture_descriptor.size.width as usize;
     let height = image.texture_descriptor.size.height as usize;
 
-    if image.texture_descriptor.format == TextureFormat::Rgba8UnormSrgb {
-        // vec of u8, each pixel is made up of [RGBA], each value is a u8
-        // ex width is 4
-        //    height is 4
-        //  first pixel would be  (y(0) * image_width(4) + x(0) * 4(size of pixel data)) = 0
-        //  5th pixel would be y = 1 * 4 = 4 + 0 = 4 * 4 = 16
-        //  alpha of that is the last component from the calculated position so its index + 3
-        //
-        //          0    1    2    3 --> x
-        // so its 0 RGBA,RGBA,RGBA,RGBA
-        // so its 1 RGBA,RGBA,RGBA,RGBA
-        // so its 2 RGBA,RGBA,RGBA,RGBA
-        // so its 3 RGBA,RGBA,RGBA,RGBA
-        //        y
-        // row 1(second row) pixel 15(0 index)
-        // y = 1
-        // x = 15
-        for y in 0..height {
-            for x in 0..width {
-                let pixel_index = (y * width + x) * 4;
-                let pixel_alpha = pixel_data[pixel_index + 3];
-
-                // realign the pixels around the center of the image
-                let x_f = (x as f32) - (width as f32) / 2.;
-                let y_f = (y as f32) - (height as f32) / 2.;
-
-                // appears y coord needs a flip for some reason
-                // for some reason these are constructed differently from the textures so they were
-                // not matching
-                if pixel_alpha > 0 {
-                    visible_points.push(Vec2::new(x_f as f32, -y_f as f32))
+    let format = image.texture_descriptor.format;
+    let (pixel_size, alpha) = alpha_reader(format).ok_or(HullError::UnsupportedFormat(format))?;
+
+    let expected = width * height * pixel_size;
+    if pixel_data.len() < expected {
+        return Err(HullError::MissingPixelData {
+            expected,
+            found: pixel_data.len(),
+        });
+    }
+
+    // pixels are stored row by row from the top left, each pixel_size bytes
+    // ex width is 4, height is 4 and pixels are RGBA, one byte each
+    //
+    //          0    1    2    3 --> x
+    // so its 0 RGBA,RGBA,RGBA,RGBA
+    // so its 1 RGBA,RGBA,RGBA,RGBA
+    // so its 2 RGBA,RGBA,RGBA,RGBA
+    // so its 3 RGBA,RGBA,RGBA,RGBA
+    //        y
+    // row 1(second row) pixel 3 starts at (y(1) * width(4) + x(3)) * pixel_size(4) = 28
+    Ok(pixel_data[..expected]
+        .chunks_exact(pixel_size)
+        .map(|pixel| alpha(pixel) > alpha_threshold)
+        .collect())
+}
+
+/// Where a corner of the pixel grid is around the centre of the image, with world y going up.
+fn image_point(x: usize, y: usize, width: usize, height: usize) -> Vec2 {
+    // realign the pixels around the center of the image
+    let x_f = (x as f32) - (width as f32) / 2.;
+    let y_f = (y as f32) - (height as f32) / 2.;
+
+    // image rows go down but world y goes up
+    Vec2::new(x_f, -y_f)
+}
+
+/// Every corner of the visible pixels, each once, so a hull around them holds the pixels whole
+/// rather than stopping at their top left corners.
+fn visible_pixels(mask: &[bool], width: usize, height: usize) -> Vec<Vec2> {
+    // everything past the edge of the image is transparent
+    let visible = |x: usize, y: usize| x < width && y < height && mask[y * width + x];
+
+    (0..=height)
+        .flat_map(|y| (0..=width).map(move |x| (x, y)))
+        .filter(|&(x, y)| {
+            // the up to four pixels this corner belongs to
+            visible(x, y)
+                || (x > 0 && visible(x - 1, y))
+                || (y > 0 && visible(x, y - 1))
+                || (x > 0 && y > 0 && visible(x - 1, y - 1))
+        })
+        .map(|(x, y)| image_point(x, y, width, height))
+        .collect()
+}
+
+/// The outline of the opaque pixels split into convex pieces, or `None` when they're more than
+/// one shape, the outline can't be brought down to `max_vertices` corners within `max_error`, or
+/// it can't be split, as when it pinches down to a single corner somewhere.
+fn concave_pieces(
+    mask: &[bool],
+    width: usize,
+    height: usize,
+    options: &HullOptions,
+) -> Option<Vec<Vec<Vec2>>> {
+    let outline = trace_outline(mask, width, height);
+
+    // only the first shape is traced, any other leaves pixels outside its outline
+    let one_shape = (0..mask.len()).filter(|&i| mask[i]).all(|i| {
+        let centre = image_point(i % width, i / width, width, height) + Vec2::new(0.5, -0.5);
+        contains_point(&outline, centre)
+    });
+    if !one_shape {
+        return None;
+    }
+
+    let outline = simplify_outline(&outline, options.max_vertices, options.max_error)?;
+
+    // corners on a straight line would sit on the edge of the triangles either side of them
+    let count = outline.len();
+    let outline: Vec<Vec2> = (0..count)
+        .filter(|&i| {
+            let before = outline[(i + count - 1) % count];
+            let after = outline[(i + 1) % count];
+            orientation(before, outline[i], after) != Orientation::Collinear
+        })
+        .map(|i| outline[i])
+        .collect();
+    if outline.len() < 3 {
+        return None;
+    }
+
+    let triangles = triangulate(&outline)?;
+    let pieces = convex_pieces(&outline, triangles)
+        .into_iter()
+        .map(|piece| {
+            let mut path: Vec<Vec2> = piece.iter().map(|i| outline[*i]).collect();
+            path.push(path[0]);
+            path
+        })
+        .collect();
+
+    Some(pieces)
+}
+
+/// Whether `point` is inside the closed `polygon`, counting the edges a ray from it crosses.
+fn contains_point(polygon: &[Vec2], point: Vec2) -> bool {
+    let count = polygon.len();
+    (0..count)
+        .filter(|&i| {
+            let a = polygon[i];
+            let b = polygon[(i + 1) % count];
+            (a.y > point.y) != (b.y > point.y)
+                && point.x < a.x + (point.y - a.y) / (b.y - a.y) * (b.x - a.x)
+        })
+        .count()
+        % 2
+        == 1
+}
+
+/// Walks the edges between opaque and transparent pixels around the first opaque shape found
+/// from the top left, with marching squares. Each step looks at the four pixels around a corner
+/// of the pixel grid to choose which way to go, keeping the opaque ones on the left. Returns the
+/// corners where the walk turns, which with the opaque side on the left go counter-clockwise
+/// whichever way y points.
+fn trace_outline(mask: &[bool], width: usize, height: usize) -> Vec<Vec2> {
+    const UP: IVec2 = IVec2::new(0, -1);
+    const DOWN: IVec2 = IVec2::new(0, 1);
+    const LEFT: IVec2 = IVec2::new(-1, 0);
+    const RIGHT: IVec2 = IVec2::new(1, 0);
+
+    // everything past the edge of the image is transparent
+    let opaque = |x: i32, y: i32| {
+        x >= 0
+            && y >= 0
+            && (x as usize) < width
+            && (y as usize) < height
+            && mask[y as usize * width + x as usize]
+    };
+
+    // the top left corner of the first opaque pixel is on the outline, with nothing opaque
+    // above or left of it
+    let Some(first) = mask.iter().position(|visible| *visible) else {
+        return Vec::new();
+    };
+    let start = IVec2::new((first % width) as i32, (first / width) as i32);
+
+    let mut outline = Vec::new();
+    let mut corner = start;
+    let mut previous = IVec2::ZERO;
+    loop {
+        // one bit for each pixel touching the corner
+        let state = opaque(corner.x - 1, corner.y - 1) as u8
+            | (opaque(corner.x, corner.y - 1) as u8) << 1
+            | (opaque(corner.x - 1, corner.y) as u8) << 2
+            | (opaque(corner.x, corner.y) as u8) << 3;
+
+        let step = match state {
+            1 | 5 | 13 => UP,
+            2 | 3 | 7 => RIGHT,
+            4 | 12 | 14 => LEFT,
+            8 | 10 | 11 => DOWN,
+            // two pixels touching only at this corner, keep turning the same way to stay on the
+            // shape being walked
+            6 if previous == UP => LEFT,
+            6 => RIGHT,
+            9 if previous == RIGHT => UP,
+            9 => DOWN,
+            // a corner entirely inside or outside the shape isn't on its outline
+            _ => unreachable!("walked off the outline"),
+        };
+
+        if step != previous {
+            outline.push(image_point(
+                corner.x as usize,
+                corner.y as usize,
+                width,
+                height,
+            ));
+        }
+
+        corner += step;
+        previous = step;
+        if corner == start {
+            break;
+        }
+    }
+
+    outline
+}
+
+/// Drops corners from a closed counter-clockwise outline, the one nearest the line between its
+/// neighbours first, while it's within `max_error` of it or there are more than `max_vertices`.
+/// Only corners that dent into the outline or sit on a straight line are dropped, and only when
+/// the outline doesn't touch the triangle that fills in, so it only ever grows over transparent
+/// pixels and still holds the whole sprite. `None` when that leaves more than `max_vertices`.
+fn simplify_outline(
+    outline: &[Vec2],
+    max_vertices: Option<usize>,
+    max_error: f32,
+) -> Option<Vec<Vec2>> {
+    let max_vertices = max_vertices.unwrap_or(usize::MAX).max(3);
+    let mut corners = outline.to_vec();
+
+    while corners.len() > 3 {
+        let count = corners.len();
+        let cheapest = (0..count)
+            .filter_map(|i| {
+                let before = corners[(i + count - 1) % count];
+                let corner = corners[i];
+                let after = corners[(i + 1) % count];
+                corner_fill(&corners, before, corner, after).map(|error| (i, error))
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+
+        let Some((i, error)) = cheapest else {
+            break;
+        };
+        if error > max_error {
+            break;
+        }
+
+        corners.remove(i);
+    }
+
+    (corners.len() <= max_vertices).then_some(corners)
+}
+
+/// How far the outline grows by going straight from `before` to `after` instead of by way of
+/// `corner`, or `None` when that would cut into the sprite or across the rest of the `outline`.
+fn corner_fill(outline: &[Vec2], before: Vec2, corner: Vec2, after: Vec2) -> Option<f32> {
+    match orientation(before, corner, after) {
+        // turning left keeps the opaque side on the inside, cutting across would go through it
+        Orientation::CounterClockwise => None,
+        // a corner in the middle of a straight edge does nothing, one at the end of a spike
+        // doubling back on itself would be cut off
+        Orientation::Collinear => ((corner - before).dot(after - corner) >= 0.).then_some(0.),
+        Orientation::Clockwise => {
+            // going round the dent the other way, the triangle filled in is counter-clockwise
+            let touched = outline.iter().any(|&other| {
+                other != before
+                    && other != corner
+                    && other != after
+                    && in_triangle(other, before, after, corner)
+            });
+            let chord = after - before;
+            (!touched).then(|| chord.perp_dot(corner - before).abs() / chord.length())
+        }
+    }
+}
+
+/// Splits a simple counter-clockwise polygon into triangles of its corner indices by clipping
+/// ears, corners whose triangle with their neighbours is inside the polygon with no other corner
+/// in it. `None` when no corner is an ear, which only happens when the polygon crosses or touches
+/// itself.
+fn triangulate(polygon: &[Vec2]) -> Option<Vec<[usize; 3]>> {
+    let mut remaining: Vec<usize> = (0..polygon.len()).collect();
+    let mut triangles = Vec::with_capacity(polygon.len() - 2);
+
+    while remaining.len() > 3 {
+        let count = remaining.len();
+        let ear = (0..count).find(|&i| {
+            let a = polygon[remaining[(i + count - 1) % count]];
+            let b = polygon[remaining[i]];
+            let c = polygon[remaining[(i + 1) % count]];
+
+            orientation(a, b, c) == Orientation::CounterClockwise
+                && remaining.iter().all(|&other| {
+                    let p = polygon[other];
+                    p == a || p == b || p == c || !in_triangle(p, a, b, c)
+                })
+        })?;
+
+        triangles.push([
+            remaining[(ear + count - 1) % count],
+            remaining[ear],
+            remaining[(ear + 1) % count],
+        ]);
+        remaining.remove(ear);
+    }
+
+    triangles.push([remaining[0], remaining[1], remaining[2]]);
+    Some(triangles)
+}
+
+/// Whether `p` is inside or on the edge of the counter-clockwise triangle `a`, `b`, `c`.
+fn in_triangle(p: Vec2, a: Vec2, b: Vec2, c: Vec2) -> bool {
+    (b - a).perp_dot(p - a) >= 0. && (c - b).perp_dot(p - b) >= 0. && (a - c).perp_dot(p - c) >= 0.
+}
+
+/// Joins triangles sharing an edge while the result stays convex (Hertel-Mehlhorn), so a shape
+/// needs few pieces. Each piece is a counter-clockwise list of corner indices.
+fn convex_pieces(polygon: &[Vec2], triangles: Vec<[usize; 3]>) -> Vec<Vec<usize>> {
+    let mut pieces: Vec<Vec<usize>> = triangles.into_iter().map(|t| t.to_vec()).collect();
+
+    'merge: loop {
+        for i in 0..pieces.len() {
+            for j in i + 1..pieces.len() {
+                if let Some(joined) = join_if_convex(polygon, &pieces[i], &pieces[j]) {
+                    pieces[i] = joined;
+                    pieces.swap_remove(j);
+                    continue 'merge;
                 }
             }
         }
+
+        return pieces;
+    }
+}
+
+/// The two pieces as one, if they share an edge and together they're still convex.
+fn join_if_convex(polygon: &[Vec2], a: &[usize], b: &[usize]) -> Option<Vec<usize>> {
+    for i in 0..a.len() {
+        let (from, to) = (a[i], a[(i + 1) % a.len()]);
+        // going round the same way, the other piece runs along a shared edge backwards
+        let Some(j) = (0..b.len()).find(|&j| b[j] == to && b[(j + 1) % b.len()] == from) else {
+            continue;
+        };
+
+        // round `a` from the shared edge's end back to its start, then round `b` between them
+        let joined: Vec<usize> = (1..=a.len())
+            .map(|k| a[(i + k) % a.len()])
+            .chain((2..b.len()).map(|k| b[(j + k) % b.len()]))
+            .collect();
+
+        let count = joined.len();
+        let convex = (0..count).all(|k| {
+            let before = polygon[joined[k]];
+            let corner = polygon[joined[(k + 1) % count]];
+            let after = polygon[joined[(k + 2) % count]];
+            orientation(before, corner, after) != Orientation::Clockwise
+        });
+
+        return convex.then_some(joined);
     }
 
-    visible_points
+    None
 }
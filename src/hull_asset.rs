@@ -0,0 +1,238 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext, ReadAssetBytesError},
+    prelude::*,
+};
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+
+use crate::load_image_from_disk;
+use crate::{Hull, HullError, HullOptions};
+
+/// Bump whenever tracing changes, so sidecars traced the old way are traced again.
+const SIDECAR_VERSION: u32 = 3;
+
+/// A sprite's collision hull, normalised to the sprite like [`Hull::new`] makes them. Entities
+/// share one through a `Handle<HullAsset>` and it's stretched to the size each sprite is drawn at
+/// when the world hull is placed.
+///
+/// Tracing a hull scans every pixel of the sprite, so the result is kept in a `.hull.ron` sidecar
+/// next to the image and [`HullAssetLoader`] loads that instead. A sidecar is only used while the
+/// image's bytes still hash to what it was traced from; otherwise the load fails and the sprite
+/// is traced again in memory with [`trace_sprite_hull`]. Sidecars are only ever written by
+/// [`write_sprite_hull`], which `--write-hulls` runs for every sprite.
+#[derive(Asset, TypePath, Clone, Debug, Deref)]
+pub struct HullAsset {
+    #[deref]
+    pub hull: Hull,
+    /// How the hull was traced, a hull traced with other options than the game wants is traced
+    /// again
+    pub options: HullOptions,
+}
+
+/// The contents of a `.hull.ron` file.
+#[derive(Serialize, Deserialize)]
+struct Sidecar {
+    version: u32,
+    /// File name of the image the hull was traced from, in the same directory as the sidecar
+    image: String,
+    /// Hash of the image file's bytes when it was traced
+    image_hash: u64,
+    options: HullOptions,
+    /// Closed counter-clockwise paths, normalised to the image
+    pieces: Vec<Vec<Vec2>>,
+}
+
+#[derive(Debug)]
+pub enum HullAssetError {
+    Io(std::io::Error),
+    Parse(ron::error::SpannedError),
+    ReadImage(ReadAssetBytesError),
+    /// The image has no hull to save
+    Trace(HullError),
+    /// The sidecar was written by a different version of the tracer
+    Version(u32),
+    /// The image has changed since the sidecar was traced from it
+    OutOfDate(String),
+    /// A piece has too few corners to be a closed shape
+    Invalid,
+}
+
+impl fmt::Display for HullAssetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HullAssetError::Io(err) => write!(f, "{}", err),
+            HullAssetError::Parse(err) => write!(f, "{}", err),
+            HullAssetError::ReadImage(err) => write!(f, "{}", err),
+            HullAssetError::Trace(err) => write!(f, "{}", err),
+            HullAssetError::Version(version) => write!(
+                f,
+                "traced by version {} of the tracer, expected {}",
+                version, SIDECAR_VERSION
+            ),
+            HullAssetError::OutOfDate(image) => write!(f, "{} has changed since", image),
+            HullAssetError::Invalid => write!(f, "every piece needs at least three corners"),
+        }
+    }
+}
+
+impl std::error::Error for HullAssetError {}
+
+impl Sidecar {
+    fn parse(contents: &[u8]) -> Result<Sidecar, HullAssetError> {
+        let sidecar: Sidecar = ron::de::from_bytes(contents).map_err(HullAssetError::Parse)?;
+
+        if sidecar.version != SIDECAR_VERSION {
+            return Err(HullAssetError::Version(sidecar.version));
+        }
+        // closed paths repeat the first corner, so a triangle is four points
+        if sidecar.pieces.is_empty() || sidecar.pieces.iter().any(|piece| piece.len() < 4) {
+            return Err(HullAssetError::Invalid);
+        }
+
+        Ok(sidecar)
+    }
+
+    /// The hull, as long as `image_bytes` are what it was traced from.
+    fn into_asset(self, image_bytes: &[u8]) -> Result<HullAsset, HullAssetError> {
+        if content_hash(image_bytes) != self.image_hash {
+            return Err(HullAssetError::OutOfDate(self.image));
+        }
+
+        Ok(HullAsset {
+            hull: Hull::from_pieces(self.pieces),
+            options: self.options,
+        })
+    }
+}
+
+#[derive(Default)]
+pub struct HullAssetLoader;
+
+impl AssetLoader for HullAssetLoader {
+    type Asset = HullAsset;
+    type Settings = ();
+    type Error = HullAssetError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        load_context: &'a mut LoadContext<'_>,
+    ) -> Result<HullAsset, HullAssetError> {
+        let mut contents = Vec::new();
+        reader
+            .read_to_end(&mut contents)
+            .await
+            .map_err(HullAssetError::Io)?;
+        let sidecar = Sidecar::parse(&contents)?;
+
+        // only the image's bytes are needed to check it, not its pixels
+        let image_path = load_context.path().with_file_name(&sidecar.image);
+        let image_bytes = load_context
+            .read_asset_bytes(image_path)
+            .await
+            .map_err(HullAssetError::ReadImage)?;
+
+        sidecar.into_asset(&image_bytes)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["hull.ron"]
+    }
+}
+
+/// Where the hull of the image at `image_path` is kept, `ship.png` has `ship.hull.ron`.
+pub fn sidecar_path(image_path: &Path) -> PathBuf {
+    image_path.with_extension("hull.ron")
+}
+
+/// Reads the sidecar of the image at `image_path`, whose file holds `image_bytes`, without going
+/// through the asset server.
+pub fn read_sidecar(image_path: &Path, image_bytes: &[u8]) -> Result<HullAsset, HullAssetError> {
+    let contents = std::fs::read(sidecar_path(image_path)).map_err(HullAssetError::Io)?;
+    Sidecar::parse(&contents)?.into_asset(image_bytes)
+}
+
+/// Saves `asset` as the sidecar of the image at `image_path`, whose file holds `image_bytes`.
+pub fn write_sidecar(
+    image_path: &Path,
+    image_bytes: &[u8],
+    asset: &HullAsset,
+) -> std::io::Result<()> {
+    let image = image_path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    let sidecar = Sidecar {
+        version: SIDECAR_VERSION,
+        image_hash: content_hash(image_bytes),
+        options: asset.options.clone(),
+        pieces: asset.hull.pieces().to_vec(),
+        image,
+    };
+
+    // one line per piece
+    let config = PrettyConfig::new().depth_limit(2);
+    let contents = ron::ser::to_string_pretty(&sidecar, config)
+        .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
+
+    std::fs::write(
+        sidecar_path(image_path),
+        format!(
+            "// Collision hull traced from {} by `stroids --write-hulls`. Run that again after\n\
+             // changing the image or how it's traced.\n{}\n",
+            sidecar.image, contents
+        ),
+    )
+}
+
+/// Traces the hull of the sprite at `image_path` and saves it as the sprite's sidecar.
+pub fn write_sprite_hull(image_path: &Path, options: &HullOptions) -> Result<(), HullAssetError> {
+    let image_bytes = std::fs::read(image_path).map_err(HullAssetError::Io)?;
+    let hull =
+        Hull::new(&load_image_from_disk(image_path), options).map_err(HullAssetError::Trace)?;
+
+    let asset = HullAsset {
+        hull,
+        options: options.clone(),
+    };
+    write_sidecar(image_path, &image_bytes, &asset).map_err(HullAssetError::Io)
+}
+
+/// Traces the hull of a sprite without an up to date sidecar from its decoded `image`. The hull
+/// is only kept in memory, so every startup traces it again until `--write-hulls` saves it. If
+/// the image can't be traced the sprite collides with its whole rectangle instead.
+pub fn trace_sprite_hull(name: &str, image: &Image, options: &HullOptions) -> HullAsset {
+    let hull = match Hull::new(image, options) {
+        Ok(hull) => {
+            info!(
+                "traced the {} collision hull, run with --write-hulls to save it",
+                name
+            );
+            hull
+        }
+        Err(err) => {
+            error!(
+                "can't trace a collision hull for the {}: {}, colliding with the whole image instead",
+                name, err
+            );
+            Hull::unit_square()
+        }
+    };
+
+    HullAsset {
+        hull,
+        options: options.clone(),
+    }
+}
+
+/// FNV-1a, which unlike the standard library's hasher is the same from one build to the next.
+fn content_hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
@@ -1,6 +1,20 @@
 use bevy::prelude::*;
 
-use crate::{AsteroidCount, GameState};
+use crate::{AsteroidCount, GameState, InGameSet};
+
+pub struct LevelPlugin;
+
+impl Plugin for LevelPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<LevelUpEvent>()
+            .add_systems(Startup, setup_level)
+            .add_systems(OnEnter(GameState::NewGame), reset_level)
+            .add_systems(
+                FixedUpdate,
+                level_completion_watcher.in_set(InGameSet::Progress),
+            );
+    }
+}
 
 #[derive(Component, Clone, Debug)]
 pub struct Level(pub u32);
@@ -12,19 +26,24 @@ pub fn setup_level(mut commands: Commands) {
     commands.spawn(Level(1));
 }
 
+pub fn reset_level(mut level_query: Query<&mut Level>) {
+    for mut level in level_query.iter_mut() {
+        level.0 = 1;
+    }
+}
+
 pub fn level_completion_watcher(
     asteroid_query: Query<&AsteroidCount, Changed<AsteroidCount>>,
     mut level_query: Query<&mut Level>,
     mut next_state: ResMut<NextState<GameState>>,
     mut event_writer: EventWriter<LevelUpEvent>,
 ) {
-    for asteroid_count in asteroid_query.iter() {
-        if asteroid_count.0 <= 0 {
+    if let Some(asteroid_count) = asteroid_query.iter().next() {
+        if asteroid_count.0 == 0 {
             let mut level = level_query.single_mut();
             event_writer.send(LevelUpEvent(Level(level.0)));
             level.0 += 1;
             next_state.set(GameState::LevelComplete)
         }
-        return;
     }
 }
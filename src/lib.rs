@@ -0,0 +1,592 @@
+use std::path::Path;
+
+use bevy::{app::PluginGroupBuilder, asset::LoadState, prelude::*, state::app::StatesPlugin};
+use bevy_prng::WyRand;
+use bevy_rand::{
+    plugin::EntropyPlugin,
+    prelude::{EntropyComponent, ForkableRng, GlobalEntropy, GlobalRngSeed},
+};
+use rand_core::{RngCore, SeedableRng};
+
+pub mod constants;
+use constants::*;
+
+pub mod physics;
+pub use physics::*;
+
+pub mod player;
+pub use player::*;
+
+pub mod asteroid;
+pub use asteroid::*;
+
+pub mod saucer;
+pub use saucer::*;
+
+pub mod boundary;
+pub use boundary::*;
+
+pub mod display;
+pub use display::*;
+
+pub mod ghost;
+pub use ghost::*;
+
+pub mod hull;
+pub use hull::*;
+
+pub mod hull_asset;
+pub use hull_asset::*;
+
+pub mod spatial_hash;
+pub use spatial_hash::*;
+
+pub mod ui;
+pub use ui::*;
+
+pub mod scoring;
+pub use scoring::*;
+
+pub mod level;
+pub use level::*;
+
+pub mod headless;
+pub use headless::*;
+
+pub mod replay;
+pub use replay::*;
+
+pub mod highscore;
+pub use highscore::*;
+
+pub mod tuning;
+pub use tuning::*;
+
+pub mod pause;
+pub use pause::*;
+
+#[derive(States, Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub enum GameState {
+    #[default]
+    Loading,
+    Processing,
+    NewGame,
+    InGame,
+    Paused,
+    /// Passed straight through to tear the level down and build it again
+    RestartLevel,
+    GameOver,
+    EnterInitials,
+    LevelComplete,
+}
+
+/// A game with a ship and asteroid field in it, either `InGame` or `Paused`. Entering builds the
+/// field, leaving tears it down, pausing and resuming does neither.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Playing;
+
+impl ComputedStates for Playing {
+    type SourceStates = GameState;
+
+    fn compute(sources: GameState) -> Option<Self> {
+        matches!(sources, GameState::InGame | GameState::Paused).then_some(Playing)
+    }
+}
+
+/// Ordering of the gameplay systems while `InGame`. Each plugin drops its systems into one of
+/// these so the chain holds together no matter which plugins are added.
+///
+/// Everything but `Ui` runs in `FixedUpdate` around the [`PhysicsSet`]s, so a game only depends
+/// on the seed and the [`PlayerInput`] of each tick, never on the frame rate.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum InGameSet {
+    Input,
+    Spawn,
+    Progress,
+    Score,
+    Ui,
+}
+
+/// Every plugin needed to play the game, in the order they should be added.
+pub struct StroidsPlugin;
+
+impl PluginGroup for StroidsPlugin {
+    fn build(self) -> PluginGroupBuilder {
+        PluginGroupBuilder::start::<Self>()
+            .add(CorePlugin::default())
+            .add(TuningPlugin::default())
+            .add(LoadingPlugin)
+            .add(PhysicsPlugin)
+            .add(GhostPlugin)
+            .add(PlayerPlugin)
+            .add(AsteroidPlugin)
+            .add(SaucerPlugin)
+            .add(ScoringPlugin)
+            .add(LevelPlugin)
+            .add(PausePlugin)
+            .add(HighScorePlugin::default())
+            .add(UiPlugin)
+            .add(DisplayPlugin)
+    }
+}
+
+/// Game state machine, the global RNG and the system sets the other plugins slot into.
+///
+/// With a `seed` the whole game is reproducible, otherwise one is drawn from OS entropy. Either
+/// way the seed in use ends up in the [`GameSeed`] resource, and each game after the first is
+/// seeded from it in turn.
+#[derive(Default)]
+pub struct CorePlugin {
+    pub seed: Option<u64>,
+}
+
+/// The seed of the game being played. The first game of a session gets the seed the global RNG
+/// was started from and every game after that the next one the global RNG draws, so a session
+/// started from the same seed plays the same games. Each new game forks the RNG streams of the
+/// other plugins off its seed, so the same seed always gives the same game.
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct GameSeed(pub u64);
+
+impl Plugin for CorePlugin {
+    fn build(&self, app: &mut App) {
+        // MinimalPlugins doesn't bring state support along
+        if !app.is_plugin_added::<StatesPlugin>() {
+            app.add_plugins(StatesPlugin);
+        }
+
+        let entropy = match self.seed {
+            Some(seed) => EntropyPlugin::<WyRand>::with_seed(seed.to_le_bytes()),
+            None => EntropyPlugin::<WyRand>::default(),
+        };
+        app.add_plugins(entropy);
+
+        let seed = app.world().resource::<GlobalRngSeed<WyRand>>().get_seed();
+        app.insert_resource(GameSeed(u64::from_le_bytes(seed)));
+
+        app.init_resource::<CollisionHullOptions>()
+            .init_state::<GameState>()
+            .add_computed_state::<Playing>()
+            .configure_sets(
+                FixedUpdate,
+                (
+                    InGameSet::Input,
+                    InGameSet::Spawn,
+                    PhysicsSet::Movement,
+                    PhysicsSet::Constraints,
+                    PhysicsSet::Bounds,
+                    PhysicsSet::Collision,
+                    InGameSet::Progress,
+                    InGameSet::Score,
+                )
+                    .chain()
+                    .run_if(in_state(GameState::InGame).and_then(no_transition_pending)),
+            )
+            .configure_sets(Update, InGameSet::Ui.run_if(in_state(GameState::InGame)))
+            // Always run the despawner, once per tick as well so a despawned entity never lives
+            // into the next tick however many ticks a frame runs
+            .add_systems(Update, despawner)
+            .add_systems(FixedPostUpdate, despawner)
+            //
+            // Menu States - wait for the player to continue
+            //
+            .add_systems(
+                Update,
+                move_to_ingame.run_if(
+                    in_state(GameState::NewGame).or_else(in_state(GameState::LevelComplete)),
+                ),
+            )
+            .add_systems(
+                Update,
+                move_to_newgame.run_if(in_state(GameState::GameOver)),
+            )
+            .add_systems(OnEnter(GameState::NewGame), seed_new_game)
+            .add_systems(OnEnter(GameState::RestartLevel), restart_level)
+            //
+            // InGame
+            //
+            .add_systems(OnExit(Playing), cleanup_ingame);
+    }
+}
+
+/// Loads the sprites, font and collision hull sidecars through the asset server, then traces
+/// the hull of any sprite whose sidecar is missing or out of date. Headless apps use
+/// [`HeadlessPlugin`] instead.
+pub struct LoadingPlugin;
+
+impl Plugin for LoadingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<HullAsset>()
+            .register_asset_loader(HullAssetLoader)
+            .insert_resource(AssetsLoading::default())
+            .add_systems(Startup, load_assets)
+            //
+            // Loading State
+            //
+            .add_systems(
+                Update,
+                assets_loaded_listener.run_if(in_state(GameState::Loading)),
+            )
+            //
+            // Processing State
+            //
+            .add_systems(OnEnter(GameState::Processing), setup_collision_hulls);
+    }
+}
+
+#[derive(Component)]
+pub struct Despawning;
+
+/// The hull of every sprite, shared by all the entities drawn with it.
+#[derive(Resource, Default, Clone)]
+pub struct CollisionHulls {
+    pub ship: Handle<HullAsset>,
+    pub asteroid_lg: Handle<HullAsset>,
+    pub asteroid_m: Handle<HullAsset>,
+    pub asteroid_sm: Handle<HullAsset>,
+    pub saucer_lg: Handle<HullAsset>,
+    pub saucer_sm: Handle<HullAsset>,
+    pub projectile: Handle<HullAsset>,
+}
+
+/// How each sprite's hull is traced, read when the hulls are built in `Processing`. Insert one
+/// before then to change them; a sidecar traced with other options is traced again.
+#[derive(Resource, Clone, Debug)]
+pub struct CollisionHullOptions {
+    pub ship: HullOptions,
+    pub asteroid_lg: HullOptions,
+    pub asteroid_m: HullOptions,
+    pub asteroid_sm: HullOptions,
+    pub saucer_lg: HullOptions,
+    pub saucer_sm: HullOptions,
+}
+
+impl Default for CollisionHullOptions {
+    fn default() -> Self {
+        // a pixel of slack drops most of the corners a pixel outline has
+        let outline = HullOptions {
+            max_error: 1.,
+            ..default()
+        };
+        // asteroids are tested against everything, keep them cheap whatever the art
+        let asteroid = HullOptions {
+            max_vertices: Some(10),
+            ..outline.clone()
+        };
+        // the ship's swept back wings leave a notch a convex hull would fill in
+        let ship = HullOptions {
+            concave: true,
+            ..outline.clone()
+        };
+
+        CollisionHullOptions {
+            ship,
+            asteroid_lg: asteroid.clone(),
+            asteroid_m: asteroid.clone(),
+            asteroid_sm: asteroid,
+            saucer_lg: outline.clone(),
+            saucer_sm: outline,
+        }
+    }
+}
+
+/// Projectiles are untextured sprites, one pixel scaled up by their transform.
+fn projectile_hull() -> HullAsset {
+    HullAsset {
+        hull: Hull::unit_square(),
+        options: HullOptions::default(),
+    }
+}
+
+#[derive(Resource, Default, Clone)]
+pub struct GameAssets {
+    pub ship: Handle<Image>,
+    pub asteroid_lg: Handle<Image>,
+    pub asteroid_m: Handle<Image>,
+    pub asteroid_sm: Handle<Image>,
+    pub saucer_lg: Handle<Image>,
+    pub saucer_sm: Handle<Image>,
+    pub font: Handle<Font>,
+}
+
+#[derive(Resource, Default)]
+struct AssetsLoading {
+    assets: Vec<UntypedHandle>,
+    /// Hull sidecars, which may fail to load without holding anything up, as the sprite is
+    /// traced instead
+    hulls: Vec<UntypedHandle>,
+}
+
+/// Debug overlay of every collision hull, add it to `Update` to see what is actually colliding.
+pub fn draw_line_gizmo(
+    mut gizmos: Gizmos,
+    hulls: Res<Assets<HullAsset>>,
+    player_query: Query<(&Transform, &Sprite, &Handle<HullAsset>), With<Player>>,
+    asteroids_query: Query<
+        (&Transform, &Sprite, &AsteroidSize, &Handle<HullAsset>),
+        With<Asteroid>,
+    >,
+    saucer_query: Query<(&Transform, &Sprite, &Handle<HullAsset>), With<Saucer>>,
+) {
+    let Ok((player_transform, player_sprite, player_hull)) = player_query.get_single() else {
+        return;
+    };
+    gizmos.line_2d(
+        Vec2::ZERO,
+        // Vec2::new(MAX_X_POSITION, MAX_Y_POSITION),
+        player_transform.translation.truncate(),
+        Color::srgb(0., 1., 1.),
+    );
+
+    if let Some(hull) = hulls.get(player_hull) {
+        hull.draw_as_lines(
+            &mut gizmos,
+            Color::srgb(1.0, 0.0, 1.0),
+            &hull_transform(player_transform, player_sprite),
+        );
+    }
+
+    for (transform, sprite, size, hull) in asteroids_query.iter() {
+        let Some(hull) = hulls.get(hull) else {
+            continue;
+        };
+        let color = match size {
+            AsteroidSize::Small => Color::srgb(0.0, 0.0, 1.0),
+            AsteroidSize::Medium => Color::srgb(0.0, 1.0, 0.0),
+            AsteroidSize::Large => Color::srgb(1.0, 0.0, 0.0),
+        };
+        hull.draw_as_lines(&mut gizmos, color, &hull_transform(transform, sprite));
+    }
+
+    for (transform, sprite, hull) in saucer_query.iter() {
+        let Some(hull) = hulls.get(hull) else {
+            continue;
+        };
+        hull.draw_as_lines(
+            &mut gizmos,
+            Color::srgb(1.0, 1.0, 0.0),
+            &hull_transform(transform, sprite),
+        );
+    }
+}
+
+fn load_assets(
+    mut commands: Commands,
+    server: Res<AssetServer>,
+    mut loading: ResMut<AssetsLoading>,
+) {
+    let game_assets = GameAssets {
+        ship: server.load(SHIP_IMAGE),
+        asteroid_lg: server.load(ASTEROID_LG_IMAGE),
+        asteroid_m: server.load(ASTEROID_M_IMAGE),
+        asteroid_sm: server.load(ASTEROID_SM_IMAGE),
+        saucer_lg: server.load(SAUCER_LG_IMAGE),
+        saucer_sm: server.load(SAUCER_SM_IMAGE),
+        font: server.load(FONT),
+    };
+
+    loading.assets.push(game_assets.ship.clone().untyped());
+    loading
+        .assets
+        .push(game_assets.asteroid_lg.clone().untyped());
+    loading
+        .assets
+        .push(game_assets.asteroid_m.clone().untyped());
+    loading
+        .assets
+        .push(game_assets.asteroid_sm.clone().untyped());
+    loading.assets.push(game_assets.saucer_lg.clone().untyped());
+    loading.assets.push(game_assets.saucer_sm.clone().untyped());
+    loading.assets.push(game_assets.font.clone().untyped());
+
+    let sidecar = |image: &str| server.load(sidecar_path(Path::new(image)));
+    let collision_hulls = CollisionHulls {
+        ship: sidecar(SHIP_IMAGE),
+        asteroid_lg: sidecar(ASTEROID_LG_IMAGE),
+        asteroid_m: sidecar(ASTEROID_M_IMAGE),
+        asteroid_sm: sidecar(ASTEROID_SM_IMAGE),
+        saucer_lg: sidecar(SAUCER_LG_IMAGE),
+        saucer_sm: sidecar(SAUCER_SM_IMAGE),
+        // added once loading is done
+        projectile: Handle::default(),
+    };
+
+    loading.hulls.push(collision_hulls.ship.clone().untyped());
+    loading
+        .hulls
+        .push(collision_hulls.asteroid_lg.clone().untyped());
+    loading
+        .hulls
+        .push(collision_hulls.asteroid_m.clone().untyped());
+    loading
+        .hulls
+        .push(collision_hulls.asteroid_sm.clone().untyped());
+    loading
+        .hulls
+        .push(collision_hulls.saucer_lg.clone().untyped());
+    loading
+        .hulls
+        .push(collision_hulls.saucer_sm.clone().untyped());
+
+    commands.insert_resource(game_assets);
+    commands.insert_resource(collision_hulls);
+}
+
+/// Traces every sprite whose sidecar didn't load, or was traced with different options, from the
+/// loaded image.
+fn setup_collision_hulls(
+    images: Res<Assets<Image>>,
+    handles: Res<GameAssets>,
+    options: Res<CollisionHullOptions>,
+    mut collision_hulls: ResMut<CollisionHulls>,
+    mut hull_assets: ResMut<Assets<HullAsset>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let mut ensure_hull =
+        |name: &str, image: &Handle<Image>, hull: &Handle<HullAsset>, options: &HullOptions| {
+            if hull_assets
+                .get(hull)
+                .is_some_and(|asset| asset.options == *options)
+            {
+                return;
+            }
+
+            let image = images.get(image).unwrap();
+            let asset = trace_sprite_hull(name, image, options);
+            hull_assets.insert(hull, asset);
+        };
+
+    ensure_hull("ship", &handles.ship, &collision_hulls.ship, &options.ship);
+    ensure_hull(
+        "large asteroid",
+        &handles.asteroid_lg,
+        &collision_hulls.asteroid_lg,
+        &options.asteroid_lg,
+    );
+    ensure_hull(
+        "medium asteroid",
+        &handles.asteroid_m,
+        &collision_hulls.asteroid_m,
+        &options.asteroid_m,
+    );
+    ensure_hull(
+        "small asteroid",
+        &handles.asteroid_sm,
+        &collision_hulls.asteroid_sm,
+        &options.asteroid_sm,
+    );
+    ensure_hull(
+        "large saucer",
+        &handles.saucer_lg,
+        &collision_hulls.saucer_lg,
+        &options.saucer_lg,
+    );
+    ensure_hull(
+        "small saucer",
+        &handles.saucer_sm,
+        &collision_hulls.saucer_sm,
+        &options.saucer_sm,
+    );
+
+    collision_hulls.projectile = hull_assets.add(projectile_hull());
+    next_state.set(GameState::NewGame);
+}
+
+/// Holds the simulation still between a system asking to leave `InGame` and the transition
+/// happening, otherwise a frame that runs several ticks would keep playing the dead game.
+fn no_transition_pending(next_state: Res<NextState<GameState>>) -> bool {
+    matches!(*next_state, NextState::Unchanged)
+}
+
+fn move_to_ingame(keys: Res<ButtonInput<KeyCode>>, mut next_state: ResMut<NextState<GameState>>) {
+    if keys.just_pressed(KeyCode::KeyP) || keys.just_pressed(KeyCode::Enter) {
+        next_state.set(GameState::InGame);
+    }
+}
+
+fn move_to_newgame(
+    keys: Res<ButtonInput<KeyCode>>,
+    score: Res<Score>,
+    high_scores: Option<Res<HighScores>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if keys.just_pressed(KeyCode::KeyP) || keys.just_pressed(KeyCode::Enter) {
+        match high_scores {
+            Some(high_scores) if high_scores.qualifies(score.0) => {
+                next_state.set(GameState::EnterInitials)
+            }
+            _ => next_state.set(GameState::NewGame),
+        }
+    }
+}
+
+/// Picks the seed of the game about to start and forks the game's RNG streams off it, always in
+/// the same order so a seed gives the same streams.
+pub fn seed_new_game(
+    mut commands: Commands,
+    mut first_game_seeded: Local<bool>,
+    mut global: ResMut<GlobalEntropy<WyRand>>,
+    mut seed: ResMut<GameSeed>,
+) {
+    if *first_game_seeded {
+        seed.0 = global.next_u64();
+    }
+    *first_game_seeded = true;
+
+    let mut game = EntropyComponent::<WyRand>::from_seed(seed.0.to_le_bytes());
+    commands.insert_resource(AsteroidFieldRng(game.fork_rng()));
+    commands.insert_resource(AsteroidSplitRng(game.fork_rng()));
+    commands.insert_resource(SaucerRng(game.fork_rng()));
+}
+
+fn restart_level(mut next_state: ResMut<NextState<GameState>>) {
+    next_state.set(GameState::InGame);
+}
+
+#[allow(clippy::type_complexity)]
+fn cleanup_ingame(
+    mut commands: Commands,
+    query: Query<
+        Entity,
+        Or<(
+            With<Player>,
+            With<Projectile>,
+            With<Asteroid>,
+            With<Saucer>,
+            With<SaucerProjectile>,
+        )>,
+    >,
+) {
+    for entity in query.iter() {
+        commands.entity(entity).try_insert(Despawning);
+    }
+}
+
+fn despawner(mut commands: Commands, query: Query<Entity, With<Despawning>>) {
+    for entity in query.iter() {
+        // along with any wrap ghosts it has
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn assets_loaded_listener(
+    server: Res<AssetServer>,
+    handles: Res<AssetsLoading>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let all_loaded = handles
+        .assets
+        .iter()
+        .all(|h| server.load_state(h) == LoadState::Loaded);
+
+    // a missing or out of date sidecar fails to load, the sprite is traced in `Processing`
+    let hulls_settled = handles.hulls.iter().all(|h| {
+        matches!(
+            server.load_state(h),
+            LoadState::Loaded | LoadState::Failed(_)
+        )
+    });
+
+    if all_loaded && hulls_settled {
+        next_state.set(GameState::Processing);
+    }
+}
@@ -0,0 +1,159 @@
+use bevy::prelude::*;
+
+use crate::constants::RESOLUTIONS;
+use crate::GameState;
+
+/// [Escape] pauses the game and opens the pause menu. While `Paused` the `FixedUpdate` chain
+/// doesn't run, so everything stays exactly where it was until the game is resumed.
+pub struct PausePlugin;
+
+impl Plugin for PausePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PauseMenu>()
+            .init_resource::<Settings>()
+            .add_systems(OnEnter(GameState::Paused), reset_pause_menu)
+            .add_systems(
+                Update,
+                (
+                    pause_game.run_if(in_state(GameState::InGame)),
+                    pause_menu_controls.run_if(in_state(GameState::Paused)),
+                )
+                    .run_if(resource_exists::<ButtonInput<KeyCode>>),
+            );
+    }
+}
+
+/// Player preferences that can be changed from the pause menu.
+#[derive(Resource, Default, Clone, Debug)]
+pub struct Settings {
+    pub show_collision_hulls: bool,
+    pub fullscreen: bool,
+    /// Index into [`RESOLUTIONS`] of the windowed size
+    pub resolution: usize,
+    pub camera_scaling: CameraScaling,
+}
+
+/// How the arena's view is fitted to a window of another shape.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum CameraScaling {
+    /// Shows exactly the view, with black bars either side or above and below
+    #[default]
+    Letterbox,
+    /// Fills the window, showing more of the world along its longer side
+    Expand,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PauseMenuItem {
+    Resume,
+    RestartLevel,
+    Settings,
+    QuitToTitle,
+    ShowCollisionHulls,
+    Fullscreen,
+    Resolution,
+    CameraScaling,
+    Back,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum PausePage {
+    #[default]
+    Main,
+    Settings,
+}
+
+impl PausePage {
+    pub fn items(&self) -> &'static [PauseMenuItem] {
+        match self {
+            PausePage::Main => &[
+                PauseMenuItem::Resume,
+                PauseMenuItem::RestartLevel,
+                PauseMenuItem::Settings,
+                PauseMenuItem::QuitToTitle,
+            ],
+            PausePage::Settings => &[
+                PauseMenuItem::ShowCollisionHulls,
+                PauseMenuItem::Fullscreen,
+                PauseMenuItem::Resolution,
+                PauseMenuItem::CameraScaling,
+                PauseMenuItem::Back,
+            ],
+        }
+    }
+}
+
+#[derive(Resource, Default, Clone, Debug)]
+pub struct PauseMenu {
+    pub page: PausePage,
+    pub selected: usize,
+}
+
+impl PauseMenu {
+    pub fn selected_item(&self) -> PauseMenuItem {
+        self.page.items()[self.selected]
+    }
+
+    fn open(&mut self, page: PausePage) {
+        self.page = page;
+        self.selected = 0;
+    }
+}
+
+fn reset_pause_menu(mut menu: ResMut<PauseMenu>) {
+    *menu = PauseMenu::default();
+}
+
+fn pause_game(keys: Res<ButtonInput<KeyCode>>, mut next_state: ResMut<NextState<GameState>>) {
+    if keys.just_pressed(KeyCode::Escape) {
+        next_state.set(GameState::Paused);
+    }
+}
+
+fn pause_menu_controls(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut menu: ResMut<PauseMenu>,
+    mut settings: ResMut<Settings>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if keys.just_pressed(KeyCode::Escape) {
+        match menu.page {
+            PausePage::Main => next_state.set(GameState::InGame),
+            PausePage::Settings => menu.open(PausePage::Main),
+        }
+        return;
+    }
+
+    let item_count = menu.page.items().len();
+    if keys.just_pressed(KeyCode::ArrowUp) || keys.just_pressed(KeyCode::KeyW) {
+        menu.selected = (menu.selected + item_count - 1) % item_count;
+    }
+    if keys.just_pressed(KeyCode::ArrowDown) || keys.just_pressed(KeyCode::KeyS) {
+        menu.selected = (menu.selected + 1) % item_count;
+    }
+
+    if !keys.just_pressed(KeyCode::Enter) {
+        return;
+    }
+
+    match menu.selected_item() {
+        PauseMenuItem::Resume => next_state.set(GameState::InGame),
+        PauseMenuItem::RestartLevel => next_state.set(GameState::RestartLevel),
+        PauseMenuItem::Settings => menu.open(PausePage::Settings),
+        PauseMenuItem::QuitToTitle => next_state.set(GameState::NewGame),
+        PauseMenuItem::ShowCollisionHulls => {
+            settings.show_collision_hulls = !settings.show_collision_hulls
+        }
+        PauseMenuItem::Fullscreen => settings.fullscreen = !settings.fullscreen,
+        PauseMenuItem::Resolution => {
+            settings.resolution = (settings.resolution + 1) % RESOLUTIONS.len()
+        }
+        PauseMenuItem::CameraScaling => {
+            settings.camera_scaling = match settings.camera_scaling {
+                CameraScaling::Letterbox => CameraScaling::Expand,
+                CameraScaling::Expand => CameraScaling::Letterbox,
+            }
+        }
+        PauseMenuItem::Back => menu.open(PausePage::Main),
+    }
+}
@@ -1,17 +1,103 @@
-use crate::check_for_collision;
+use crate::apply_boundary_tuning;
+use crate::arena_boundary_system;
+use crate::check_wrapped_collision;
 use crate::constants::*;
+use crate::minimum_image;
+use crate::ArenaBoundary;
 use crate::AsteroidDestroyedEvent;
 use crate::Despawning;
 use crate::Hull;
+use crate::HullAsset;
+use crate::Invulnerable;
 use crate::Player;
 use crate::PlayerKilledEvent;
 use crate::Projectile;
+use crate::SpatialHash;
+use crate::Tuning;
+use crate::WorldHull;
 
 use crate::asteroid::*;
 
+use bevy::ecs::entity::EntityHashSet;
 use bevy::prelude::*;
 use std::f32::consts::TAU;
 
+/// Stages of the `FixedUpdate` simulation step, run in order while `InGame`.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PhysicsSet {
+    Movement,
+    Constraints,
+    Bounds,
+    Collision,
+}
+
+pub struct PhysicsPlugin;
+
+impl Plugin for PhysicsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Arena>()
+            .insert_resource(SpatialHash::new(SPATIAL_HASH_CELL_SIZE))
+            .add_systems(
+                PreUpdate,
+                update_arena_size.run_if(resource_exists_and_changed::<Tuning>),
+            )
+            .add_systems(
+                FixedUpdate,
+                (
+                    (apply_movement, apply_rotational_velocity)
+                        .chain()
+                        .in_set(PhysicsSet::Movement),
+                    (apply_boundary_tuning, arena_boundary_system)
+                        .chain()
+                        .in_set(PhysicsSet::Bounds),
+                    (
+                        update_world_hulls,
+                        update_spatial_hash,
+                        asteroid_collision_system.run_if(asteroids_collide),
+                        collision_system,
+                    )
+                        .chain()
+                        .in_set(PhysicsSet::Collision),
+                ),
+            );
+
+        #[cfg(debug_assertions)]
+        app.add_systems(Update, warn_on_hull_sprite_mismatch);
+    }
+}
+
+/// The playing field, centred on the origin. It's a screen by default and as many as the tuning's
+/// `world.screens` say otherwise. What happens at its edges is up to each entity's
+/// [`ArenaBoundary`], collisions are tested across the edges between entities that wrap.
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct Arena {
+    pub size: Vec2,
+}
+
+impl Default for Arena {
+    fn default() -> Self {
+        Arena {
+            size: Vec2::new(VIEW_WIDTH, VIEW_HEIGHT),
+        }
+    }
+}
+
+impl Arena {
+    /// Where `position` comes back into the arena, a whole arena across from where it left.
+    pub fn wrap(&self, position: Vec2) -> Vec2 {
+        position - self.size * ((position + self.size / 2.) / self.size).floor()
+    }
+
+    /// How far it is from the centre to the edge going in `direction_norm`. The arena wraps, so
+    /// it's as far from anywhere else to the edge of the arena's worth of space around it.
+    pub fn edge_distance(&self, direction_norm: Vec2) -> f32 {
+        assert!(direction_norm.is_normalized());
+        let edge_distance = self.size / 2. / direction_norm.abs();
+
+        edge_distance.min_element()
+    }
+}
+
 #[derive(Component, Clone, Copy)]
 pub struct Velocity(pub Vec2);
 
@@ -21,6 +107,30 @@ pub struct RotationalVelocity(pub f32);
 #[derive(Component, Clone, Copy)]
 pub struct Heading(pub f32);
 
+/// How hard something is to push around, and to spin, in a collision.
+#[derive(Component, Clone, Copy)]
+pub struct Mass {
+    pub mass: f32,
+    pub inertia: f32,
+}
+
+impl Mass {
+    /// A solid plate the shape of the hull, weighing one per square pixel.
+    pub fn from_hull(hull: &Hull) -> Mass {
+        let mass = hull.area();
+        Mass {
+            mass,
+            inertia: hull.moment_of_inertia(mass),
+        }
+    }
+}
+
+/// Fraction of the overlap between two asteroids undone each tick. Freshly split asteroids start
+/// on top of each other, so they're eased apart over a few ticks rather than jumping.
+const SEPARATION_RATE: f32 = 0.4;
+/// Overlap in pixels left alone, so resting contacts don't jitter.
+const OVERLAP_SLOP: f32 = 0.5;
+
 pub fn apply_movement(mut query: Query<(&mut Transform, &Heading, &Velocity)>, time: Res<Time>) {
     for (mut transform, heading, velocity) in query.iter_mut() {
         transform.rotation = Quat::from_rotation_z(heading.0);
@@ -34,91 +144,261 @@ pub fn apply_rotational_velocity(
 ) {
     for (mut heading, rotational_velocity) in query.iter_mut() {
         heading.0 += rotational_velocity.0 * time.delta_seconds();
-        heading.0 = heading.0 % TAU;
+        heading.0 %= TAU;
     }
 }
 
-pub fn out_of_bounds_system(mut query: Query<&mut Transform>) {
-    // we want to let things go out of bounds before moving them to prevent popping off the screen
-    for mut transform in query.iter_mut() {
-        let out_of_bound_offset = transform.scale.x.max(transform.scale.y);
+fn update_arena_size(tuning: Res<Tuning>, mut arena: ResMut<Arena>) {
+    let size = tuning.world.size();
+    if arena.size != size {
+        arena.size = size;
+    }
+}
 
-        let min_x_position = MIN_X_POSITION - out_of_bound_offset;
-        let max_x_position = MAX_X_POSITION + out_of_bound_offset;
-        let min_y_position = MIN_Y_POSITION - out_of_bound_offset;
-        let max_y_position = MAX_Y_POSITION + out_of_bound_offset;
+/// Where a normalised hull goes to cover its sprite: the entity's transform, stretched to the
+/// size the sprite is drawn at. Every textured sprite in the game sets a `custom_size`, untextured
+/// ones are drawn one unit across.
+pub fn hull_transform(transform: &Transform, sprite: &Sprite) -> Transform {
+    let size = sprite.custom_size.unwrap_or(Vec2::ONE);
+    transform.with_scale(transform.scale * size.extend(1.))
+}
 
-        if transform.translation.x < min_x_position {
-            transform.translation.x = max_x_position;
-        }
+/// Debug builds check every new hull against its sprite and warn, once per image and sizes, when
+/// the hull sticks out past the sprite or covers less than half of it either way. That usually
+/// means the hull, or its sidecar, was traced from a different image than the sprite shows.
+#[cfg(debug_assertions)]
+#[allow(clippy::type_complexity)]
+pub fn warn_on_hull_sprite_mismatch(
+    query: Query<(&Handle<HullAsset>, &Sprite, &Handle<Image>), Added<Handle<HullAsset>>>,
+    hulls: Res<Assets<HullAsset>>,
+    mut checked: Local<Vec<(AssetId<Image>, Vec2, AssetId<HullAsset>)>>,
+) {
+    // a hull traced from pixel corners can land up to a pixel off the sprite's edge
+    const TOLERANCE: f32 = 1.;
 
-        if transform.translation.x > max_x_position {
-            transform.translation.x = min_x_position;
-        }
+    for (hull, sprite, image) in query.iter() {
+        let Some(sprite_size) = sprite.custom_size else {
+            continue;
+        };
 
-        if transform.translation.y < min_y_position {
-            transform.translation.y = max_y_position;
+        let key = (image.id(), sprite_size, hull.id());
+        if checked.contains(&key) {
+            continue;
         }
-        if transform.translation.y > max_y_position {
-            transform.translation.y = min_y_position;
+        let Some(hull) = hulls.get(hull) else {
+            continue;
+        };
+        checked.push(key);
+
+        let normalised = hull.bounds();
+        let bounds = Rect::from_corners(normalised.min * sprite_size, normalised.max * sprite_size);
+
+        let sprite_bounds = Rect::from_center_size(Vec2::ZERO, sprite_size);
+        let sticks_out = bounds.min.cmplt(sprite_bounds.min - TOLERANCE).any()
+            || bounds.max.cmpgt(sprite_bounds.max + TOLERANCE).any();
+        let too_small = bounds.size().cmplt(sprite_size / 2.).any();
+
+        if sticks_out || too_small {
+            warn!(
+                "collision hull spans {} but its sprite is drawn at {}, check the hull was traced from the sprite's image",
+                bounds.size(),
+                sprite_size
+            );
         }
     }
 }
 
-pub fn screen_edge_distance(direction_norm: &Vec2) -> f32 {
-    assert!(direction_norm.is_normalized());
-    let abs_dir = direction_norm.abs();
+/// Places every hull where its entity ended up after this tick's movement, once, ahead of the
+/// collision checks.
+#[allow(clippy::type_complexity)]
+pub fn update_world_hulls(
+    hulls: Res<Assets<HullAsset>>,
+    mut query: Query<(
+        &Handle<HullAsset>,
+        &Sprite,
+        &Transform,
+        Option<&ArenaBoundary>,
+        &mut WorldHull,
+    )>,
+) {
+    for (hull, sprite, transform, boundary, mut world_hull) in query.iter_mut() {
+        let Some(hull) = hulls.get(hull) else {
+            continue;
+        };
+        world_hull.update(hull, &hull_transform(transform, sprite));
+        world_hull.set_bounded(boundary.is_some_and(|boundary| *boundary != ArenaBoundary::Wrap));
+    }
+}
 
-    let x_edge_dist = MAX_X_POSITION / abs_dir.x;
-    let y_edge_dist = MAX_Y_POSITION / abs_dir.y;
+/// Files every asteroid into the broad phase grid at its position for this tick.
+#[allow(clippy::type_complexity)]
+pub fn update_spatial_hash(
+    mut spatial_hash: ResMut<SpatialHash>,
+    arena: Res<Arena>,
+    asteroid_query: Query<(Entity, &WorldHull), (With<Asteroid>, Without<Despawning>)>,
+) {
+    spatial_hash.clear();
+    spatial_hash.set_wrap(Some(arena.size));
+    for (asteroid, hull) in asteroid_query.iter() {
+        spatial_hash.insert(asteroid, hull.centre(), hull.radius());
+    }
+}
 
-    x_edge_dist.min(y_edge_dist)
+fn asteroids_collide(tuning: Res<Tuning>) -> bool {
+    tuning.asteroids.collide
 }
 
+/// Bounces overlapping asteroids off each other with an elastic impulse through the contact
+/// point, changing both their velocity and spin, then eases them apart.
+#[allow(clippy::type_complexity)]
+pub fn asteroid_collision_system(
+    spatial_hash: Res<SpatialHash>,
+    arena: Res<Arena>,
+    mut asteroid_query: Query<
+        (
+            Entity,
+            &mut Transform,
+            &mut Velocity,
+            &mut RotationalVelocity,
+            &Mass,
+            &mut WorldHull,
+        ),
+        (With<Asteroid>, Without<Despawning>),
+    >,
+    mut asteroids: Local<Vec<Entity>>,
+    mut nearby: Local<Vec<Entity>>,
+) {
+    asteroids.clear();
+    asteroids.extend(asteroid_query.iter().map(|(asteroid, ..)| asteroid));
+
+    for &asteroid in asteroids.iter() {
+        let Ok((.., world_hull)) = asteroid_query.get(asteroid) else {
+            continue;
+        };
+        spatial_hash.query(world_hull.centre(), world_hull.radius(), &mut nearby);
+
+        for &other in nearby.iter() {
+            // each pair once
+            if other <= asteroid {
+                continue;
+            }
+            let Ok(
+                [(_, mut transform_a, mut velocity_a, mut spin_a, mass_a, mut world_a), (_, mut transform_b, mut velocity_b, mut spin_b, mass_b, mut world_b)],
+            ) = asteroid_query.get_many_mut([asteroid, other])
+            else {
+                continue;
+            };
+            let Some(contact) = check_wrapped_collision(&world_a, &world_b, arena.size) else {
+                continue;
+            };
+
+            let normal = contact.normal;
+            let inverse_mass_a = 1. / mass_a.mass;
+            let inverse_mass_b = 1. / mass_b.mass;
+
+            // velocities of the two surfaces at the contact point, spin included. The contact is
+            // on the first asteroid's side of any seam between them
+            let arm_a = contact.point - world_a.centre();
+            let arm_b = minimum_image(contact.point - world_b.centre(), arena.size);
+            let relative =
+                (velocity_b.0 + spin_b.0 * arm_b.perp()) - (velocity_a.0 + spin_a.0 * arm_a.perp());
+            let closing_speed = relative.dot(normal);
+
+            // only push back if they're still moving together
+            if closing_speed < 0. {
+                let turn_a = arm_a.perp_dot(normal);
+                let turn_b = arm_b.perp_dot(normal);
+                let impulse = normal * -2. * closing_speed
+                    / (inverse_mass_a
+                        + inverse_mass_b
+                        + turn_a * turn_a / mass_a.inertia
+                        + turn_b * turn_b / mass_b.inertia);
+
+                velocity_a.0 -= impulse * inverse_mass_a;
+                velocity_b.0 += impulse * inverse_mass_b;
+                spin_a.0 -= arm_a.perp_dot(impulse) / mass_a.inertia;
+                spin_b.0 += arm_b.perp_dot(impulse) / mass_b.inertia;
+            }
+
+            // lighter asteroids get pushed further
+            let separation = normal * (contact.depth - OVERLAP_SLOP).max(0.) * SEPARATION_RATE
+                / (inverse_mass_a + inverse_mass_b);
+            transform_a.translation -= (separation * inverse_mass_a).extend(0.);
+            transform_b.translation += (separation * inverse_mass_b).extend(0.);
+            world_a.translate(-separation * inverse_mass_a);
+            world_b.translate(separation * inverse_mass_b);
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
 pub fn collision_system(
     mut commands: Commands,
-    player_query: Query<(&Transform, &Hull), With<Player>>,
-    projectile_query: Query<(Entity, &Transform, &Hull), With<Projectile>>,
-    asteroid_query: Query<(Entity, &Transform, &Velocity, &AsteroidSize, &Hull), With<Asteroid>>,
+    spatial_hash: Res<SpatialHash>,
+    arena: Res<Arena>,
+    player_query: Query<&WorldHull, (With<Player>, Without<Invulnerable>)>,
+    projectile_query: Query<(Entity, &WorldHull), (With<Projectile>, Without<Despawning>)>,
+    asteroid_query: Query<
+        (&Transform, &Velocity, &AsteroidSize, &WorldHull),
+        (With<Asteroid>, Without<Despawning>),
+    >,
+    mut nearby: Local<Vec<Entity>>,
     mut asteroid_event: EventWriter<AsteroidDestroyedEvent>,
     mut player_killed_event: EventWriter<PlayerKilledEvent>,
 ) {
-    if let Ok((player_transform, player_hull)) = player_query.get_single() {
-        for (asteroid, asteroid_transform, asteroid_velocity, asteroid_size, asteroid_hull) in
-            asteroid_query.iter()
-        {
-            // check for player asteroid collisions
-            let player_collision = check_for_collision(
-                &player_hull,
-                &player_transform,
-                &asteroid_hull,
-                &asteroid_transform,
-            );
+    // the ship may be waiting to respawn or invulnerable, projectiles still fly either way
+    let player = player_query.get_single().ok();
+    let mut player_killed = false;
+
+    // check for player asteroid collisions, touching several at once still only costs one life
+    if let Some(player_hull) = player {
+        spatial_hash.query(player_hull.centre(), player_hull.radius(), &mut nearby);
+
+        player_killed = nearby.iter().any(|&asteroid| {
+            asteroid_query
+                .get(asteroid)
+                .is_ok_and(|(_, _, _, asteroid_hull)| {
+                    check_wrapped_collision(player_hull, asteroid_hull, arena.size).is_some()
+                })
+        });
+    }
 
-            if let Some(_) = player_collision {
-                player_killed_event.send(PlayerKilledEvent);
+    // an asteroid is only destroyed once, however many projectiles reach it this tick
+    let mut destroyed = EntityHashSet::default();
+
+    for (projectile, projectile_hull) in projectile_query.iter() {
+        spatial_hash.query(
+            projectile_hull.centre(),
+            projectile_hull.radius(),
+            &mut nearby,
+        );
+
+        for &asteroid in nearby.iter() {
+            if destroyed.contains(&asteroid) {
+                continue;
             }
+            let Ok((asteroid_transform, asteroid_velocity, asteroid_size, asteroid_hull)) =
+                asteroid_query.get(asteroid)
+            else {
+                continue;
+            };
 
-            for (projectile, projectile_transform, projectile_hull) in projectile_query.iter() {
-                let projectile_collision = check_for_collision(
-                    &projectile_hull,
-                    &projectile_transform,
-                    &asteroid_hull,
-                    &asteroid_transform,
-                );
-
-                if let Some(_) = projectile_collision {
-                    commands.entity(projectile).insert(Despawning);
-                    asteroid_event.send(AsteroidDestroyedEvent(
-                        asteroid,
-                        *asteroid_transform,
-                        *asteroid_velocity,
-                        *asteroid_size,
-                    ));
-                }
+            if check_wrapped_collision(projectile_hull, asteroid_hull, arena.size).is_some() {
+                commands.entity(projectile).insert(Despawning);
+                asteroid_event.send(AsteroidDestroyedEvent(
+                    asteroid,
+                    *asteroid_transform,
+                    *asteroid_velocity,
+                    *asteroid_size,
+                ));
+                destroyed.insert(asteroid);
+                // the projectile is used up on the first asteroid it hits
+                break;
             }
         }
-    } else {
-        return;
+    }
+
+    if player_killed {
+        player_killed_event.send(PlayerKilledEvent);
     }
 }
@@ -1,22 +1,62 @@
-use crate::constants::*;
+use crate::check_world_hull_collision;
 use crate::physics::Heading;
 use crate::physics::Velocity;
+use crate::ArenaBoundary;
+use crate::Asteroid;
 use crate::CollisionHulls;
 use crate::Despawning;
 use crate::GameAssets;
 use crate::GameState;
 use crate::Hull;
+use crate::HullAsset;
+use crate::InGameSet;
+use crate::PhysicsSet;
+use crate::Playing;
+use crate::Saucer;
+use crate::SaucerProjectile;
+use crate::Tuning;
+use crate::WorldHull;
 use bevy::prelude::*;
 use std::f32::consts::TAU;
 
-const ACCELERATION_TIME: f32 = 1.0; // time to reach max acceleration
-const ROTATION_TIME: f32 = 0.75; // full rotation time
-const THRUST_POWER: f32 = MAX_SPEED / ACCELERATION_TIME;
-const ROTATION_SPEED: f32 = TAU / ROTATION_TIME;
-const SHIP_SIZE: Vec2 = Vec2::new(64., 32.);
-const PROJECTILE_BASE_VELOCITY: f32 = MAX_SPEED;
 const SHIP_COLOR: Color = Color::srgb(1., 1., 1.);
 const PROJECTILE_COLOR: Color = Color::WHITE;
+const BLINK_INTERVAL: f32 = 0.1;
+const RESPAWN_REGION_SEGMENTS: usize = 16;
+
+pub struct PlayerPlugin;
+
+impl Plugin for PlayerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ProjectileFiredEvent>()
+            .add_event::<PlayerKilledEvent>()
+            .init_resource::<PlayerInput>()
+            .add_systems(Startup, setup_lives)
+            .add_systems(OnEnter(Playing), setup_player)
+            .add_systems(
+                Update,
+                read_keyboard_input.run_if(
+                    in_state(GameState::InGame).and_then(resource_exists::<ButtonInput<KeyCode>>),
+                ),
+            )
+            .add_systems(
+                FixedUpdate,
+                (
+                    player_controls.in_set(InGameSet::Input),
+                    (projectile_spawner, player_respawner, invulnerability_system)
+                        .in_set(InGameSet::Spawn),
+                    (distance_tracker, speed_limit_system)
+                        .chain()
+                        .in_set(PhysicsSet::Constraints),
+                    player_killed_listener
+                        .in_set(InGameSet::Progress)
+                        .after(crate::level_completion_watcher),
+                ),
+            )
+            .add_systems(OnExit(Playing), cancel_respawn)
+            .add_systems(OnEnter(GameState::NewGame), reset_lives);
+    }
+}
 
 #[derive(Component)]
 pub struct Player;
@@ -27,21 +67,56 @@ pub struct PlayerBundle {
     pub velocity: Velocity,
     pub heading: Heading,
     pub sprite_bundle: SpriteBundle,
-    pub hull: Hull,
+    pub hull: Handle<HullAsset>,
+    pub world_hull: WorldHull,
+    pub boundary: ArenaBoundary,
 }
 
 #[derive(Component)]
 pub struct Lives(pub u8);
 
+/// Counts down to the ship coming back after a death, the asteroid field carries on meanwhile.
+/// Once it runs out the ship still waits for the centre to be clear of asteroids.
+#[derive(Resource)]
+pub struct PlayerRespawn(pub Timer);
+
+impl PlayerRespawn {
+    pub fn waiting_for_clear_space(&self) -> bool {
+        self.0.finished()
+    }
+}
+
+/// Asteroids pass straight through the ship until this runs out, and the ship blinks.
+#[derive(Component)]
+pub struct Invulnerable(pub Timer);
+
+/// The controls the player is holding for the next `FixedUpdate` tick. Filled in from the
+/// keyboard every frame, but anything else (a replay, a bot) can write it instead.
+#[derive(Resource, Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct PlayerInput {
+    pub thrust: bool,
+    pub rotate_left: bool,
+    pub rotate_right: bool,
+    /// Latched until a tick consumes it so a tap between ticks isn't lost
+    pub fire: bool,
+}
+
 #[derive(Component)]
 pub struct Projectile;
 
+/// Despawns whatever it's on once it has flown `max`.
 #[derive(Component)]
 pub struct TravelDistance {
     current: f32,
     max: f32,
 }
 
+impl TravelDistance {
+    pub fn new(max: f32) -> TravelDistance {
+        TravelDistance { current: 0., max }
+    }
+}
+
 #[derive(Bundle)]
 struct ProjectileBundle {
     projectile: Projectile,
@@ -49,7 +124,9 @@ struct ProjectileBundle {
     start_velocity: Velocity,
     travel_distance: TravelDistance,
     sprite_bundle: SpriteBundle,
-    hull: Hull,
+    hull: Handle<HullAsset>,
+    world_hull: WorldHull,
+    boundary: ArenaBoundary,
 }
 
 #[derive(Event)]
@@ -59,16 +136,22 @@ pub struct ProjectileFiredEvent(Heading, Velocity, Vec2);
 pub struct PlayerKilledEvent;
 
 impl PlayerBundle {
-    pub fn new(assets: Res<GameAssets>, collision_hulls: Res<CollisionHulls>) -> PlayerBundle {
+    pub fn new(
+        assets: Res<GameAssets>,
+        collision_hulls: Res<CollisionHulls>,
+        tuning: &Tuning,
+    ) -> PlayerBundle {
         PlayerBundle {
             player: Player,
             velocity: Velocity(Vec2::new(0., 0.)),
             heading: Heading(0.25 * TAU),
             hull: collision_hulls.ship.clone(),
+            world_hull: WorldHull::default(),
+            boundary: ArenaBoundary::default(),
             sprite_bundle: SpriteBundle {
                 sprite: Sprite {
                     color: SHIP_COLOR,
-                    custom_size: Some(SHIP_SIZE),
+                    custom_size: Some(tuning.ship.size),
                     ..default()
                 },
                 transform: Transform { ..default() },
@@ -85,13 +168,16 @@ impl ProjectileBundle {
         start_velocity: Vec2,
         start: Vec2,
         collision_hulls: &CollisionHulls,
+        tuning: &Tuning,
     ) -> ProjectileBundle {
         let heading_vec = Vec2::new(heading.cos(), heading.sin());
-        let velo = start_velocity + heading_vec * PROJECTILE_BASE_VELOCITY;
+        let velo = start_velocity + heading_vec * tuning.projectile.speed * tuning.ship.max_speed();
         ProjectileBundle {
             projectile: Projectile,
             heading: Heading(heading),
             hull: collision_hulls.projectile.clone(),
+            world_hull: WorldHull::default(),
+            boundary: ArenaBoundary::default(),
             // ultimately heading doesn't really matter here, what we need is a velocity vector in
             // the right direction based on the ship's heading
             start_velocity: Velocity(velo),
@@ -107,41 +193,63 @@ impl ProjectileBundle {
                 },
                 ..default()
             },
-            travel_distance: TravelDistance {
-                current: 0.,
-                max: WINDOW_WIDTH,
-            },
+            travel_distance: TravelDistance::new(tuning.projectile.range),
         }
     }
 }
 
+pub fn setup_lives(mut commands: Commands, tuning: Res<Tuning>) {
+    commands.spawn(Lives(tuning.ship.lives));
+}
+
+pub fn reset_lives(mut commands: Commands, mut query: Query<&mut Lives>, tuning: Res<Tuning>) {
+    let maybe_lives = query.get_single_mut();
+
+    if let Ok(mut lives) = maybe_lives {
+        lives.0 = tuning.ship.lives;
+    } else {
+        commands.spawn(Lives(tuning.ship.lives));
+    }
+}
+
 pub fn setup_player(
     mut commands: Commands,
     game_assets: Res<GameAssets>,
     collision_hulls: Res<CollisionHulls>,
+    tuning: Res<Tuning>,
 ) {
-    commands.spawn(PlayerBundle::new(game_assets, collision_hulls));
+    commands.spawn(PlayerBundle::new(game_assets, collision_hulls, &tuning));
+}
+
+pub fn read_keyboard_input(keys: Res<ButtonInput<KeyCode>>, mut input: ResMut<PlayerInput>) {
+    input.thrust = keys.pressed(KeyCode::KeyW);
+    input.rotate_left = keys.pressed(KeyCode::KeyA);
+    input.rotate_right = keys.pressed(KeyCode::KeyD);
+    input.fire |= keys.just_pressed(KeyCode::Space);
 }
 
 pub fn player_controls(
     mut query: Query<(&mut Velocity, &mut Heading, &Transform, &Sprite), With<Player>>,
-    keys: Res<ButtonInput<KeyCode>>,
+    mut input: ResMut<PlayerInput>,
     time: Res<Time>,
+    tuning: Res<Tuning>,
     mut ev_fire: EventWriter<ProjectileFiredEvent>,
 ) {
+    let fire = std::mem::take(&mut input.fire);
+
     if let Ok((mut velocity, mut heading, transform, sprite)) = query.get_single_mut() {
-        if keys.pressed(KeyCode::KeyD) {
-            heading.0 -= ROTATION_SPEED * time.delta_seconds();
+        if input.rotate_right {
+            heading.0 -= tuning.ship.rotation_speed() * time.delta_seconds();
         }
-        if keys.pressed(KeyCode::KeyA) {
-            heading.0 += ROTATION_SPEED * time.delta_seconds();
+        if input.rotate_left {
+            heading.0 += tuning.ship.rotation_speed() * time.delta_seconds();
         }
 
-        heading.0 = heading.0 % TAU;
+        heading.0 %= TAU;
 
         let heading_vec = Vec2::new(heading.0.cos(), heading.0.sin());
 
-        if keys.just_pressed(KeyCode::Space) {
+        if fire {
             let size = sprite.custom_size.unwrap();
             let firing_start_pt = size.x * 0.5 + 5.;
             let ship_front = Vec2::new(
@@ -156,38 +264,121 @@ pub fn player_controls(
             ));
         }
 
-        if keys.pressed(KeyCode::KeyW) {
-            let thrust = heading_vec * THRUST_POWER * time.delta_seconds();
+        if input.thrust {
+            let thrust = heading_vec * tuning.ship.thrust_power() * time.delta_seconds();
             velocity.0 += thrust;
         }
     }
 }
 
 pub fn player_killed_listener(
+    mut commands: Commands,
     mut event: EventReader<PlayerKilledEvent>,
     mut query: Query<&mut Lives>,
+    player_query: Query<Entity, With<Player>>,
+    tuning: Res<Tuning>,
     mut next_state: ResMut<NextState<GameState>>,
 ) {
     if event.is_empty() {
         return;
     }
-    for _ev in event.read() {
-        let mut lives = query.single_mut();
-        lives.0 -= 1;
+    // an asteroid and a saucer can both get the ship in the same tick, it only dies once
+    event.clear();
 
-        if lives.0 == 0 {
-            next_state.set(GameState::GameOver)
+    for player in player_query.iter() {
+        commands.entity(player).insert(Despawning);
+    }
+
+    let mut lives = query.single_mut();
+    lives.0 -= 1;
+
+    if lives.0 == 0 {
+        next_state.set(GameState::GameOver)
+    } else {
+        commands.insert_resource(PlayerRespawn(Timer::from_seconds(
+            tuning.ship.respawn_delay,
+            TimerMode::Once,
+        )));
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn player_respawner(
+    mut commands: Commands,
+    respawn: Option<ResMut<PlayerRespawn>>,
+    game_assets: Res<GameAssets>,
+    collision_hulls: Res<CollisionHulls>,
+    hazard_query: Query<
+        &WorldHull,
+        (
+            Or<(With<Asteroid>, With<Saucer>, With<SaucerProjectile>)>,
+            Without<Despawning>,
+        ),
+    >,
+    tuning: Res<Tuning>,
+    time: Res<Time>,
+) {
+    let Some(mut respawn) = respawn else {
+        return;
+    };
+
+    if !respawn.0.tick(time.delta()).finished() {
+        return;
+    }
+
+    let spawn_region = WorldHull::new(
+        &Hull::circle(tuning.ship.respawn_clear_radius, RESPAWN_REGION_SEGMENTS),
+        &Transform::default(),
+    );
+    // anything spawned this tick hasn't been placed yet, so it can't be shown to be clear until
+    // the next one
+    let clear = hazard_query
+        .iter()
+        .all(|hull| !hull.is_empty() && check_world_hull_collision(&spawn_region, hull).is_none());
+
+    if clear {
+        commands.remove_resource::<PlayerRespawn>();
+        commands.spawn((
+            PlayerBundle::new(game_assets, collision_hulls, &tuning),
+            Invulnerable(Timer::from_seconds(
+                tuning.ship.invulnerability_time,
+                TimerMode::Once,
+            )),
+        ));
+    }
+}
+
+pub fn invulnerability_system(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Invulnerable, &mut Visibility)>,
+    time: Res<Time>,
+) {
+    for (entity, mut invulnerable, mut visibility) in query.iter_mut() {
+        if invulnerable.0.tick(time.delta()).finished() {
+            commands.entity(entity).remove::<Invulnerable>();
+            *visibility = Visibility::Inherited;
         } else {
-            next_state.set(GameState::Died)
+            let blink = (invulnerable.0.elapsed_secs() / BLINK_INTERVAL) as u32;
+            *visibility = if blink.is_multiple_of(2) {
+                Visibility::Hidden
+            } else {
+                Visibility::Inherited
+            };
         }
     }
 }
 
-pub fn speed_limit_system(mut query: Query<&mut Velocity, With<Player>>) {
+/// A level that ends while the ship is waiting to come back starts the next one with a new ship.
+pub fn cancel_respawn(mut commands: Commands) {
+    commands.remove_resource::<PlayerRespawn>();
+}
+
+pub fn speed_limit_system(mut query: Query<&mut Velocity, With<Player>>, tuning: Res<Tuning>) {
+    let max_speed = tuning.ship.max_speed();
     for mut velo in query.iter_mut() {
         let speed_squared = velo.0.length_squared();
-        if speed_squared > MAX_SPEED_SQUARED {
-            velo.0 = velo.0.normalize() * MAX_SPEED;
+        if speed_squared > max_speed * max_speed {
+            velo.0 = velo.0.normalize() * max_speed;
         }
     }
 }
@@ -196,6 +387,7 @@ pub fn projectile_spawner(
     mut commands: Commands,
     mut ev_fire: EventReader<ProjectileFiredEvent>,
     collision_hulls: Res<CollisionHulls>,
+    tuning: Res<Tuning>,
 ) {
     for ev in ev_fire.read() {
         let (heading, velocity, location) = (ev.0, ev.1, ev.2);
@@ -204,6 +396,7 @@ pub fn projectile_spawner(
             velocity.0,
             location,
             &collision_hulls,
+            &tuning,
         ));
     }
 }
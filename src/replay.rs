@@ -0,0 +1,376 @@
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+use bevy::{app::FixedMain, prelude::*};
+
+use crate::{GameSeed, GameState, InGameSet, PlayerInput};
+
+const REPLAY_MAGIC: &[u8; 4] = b"STRP";
+const REPLAY_VERSION: u8 = 2;
+const FAST_FORWARD_SPEED: f32 = 4.;
+
+const THRUST_BIT: u8 = 1;
+const ROTATE_LEFT_BIT: u8 = 1 << 1;
+const ROTATE_RIGHT_BIT: u8 = 1 << 2;
+const FIRE_BIT: u8 = 1 << 3;
+
+/// Everything needed to play a session back: the seed, the input of every `InGame` tick and the
+/// ticks the level was restarted before.
+///
+/// On disk it's a `STRP` magic, a version byte, the seed, tick count and restart count as little
+/// endian `u64`, `u32` and `u32`, then one byte of input flags per tick and a `u32` tick for each
+/// restart.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Replay {
+    pub seed: u64,
+    pub inputs: Vec<PlayerInput>,
+    /// The ticks that ran straight after restarting the level from the pause menu, in order
+    pub restarts: Vec<u32>,
+}
+
+impl Replay {
+    pub fn load(path: &Path) -> io::Result<Replay> {
+        let mut file = std::fs::File::open(path)?;
+
+        let mut header = [0u8; 4 + 1 + 8 + 4 + 4];
+        file.read_exact(&mut header)?;
+
+        if &header[0..4] != REPLAY_MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a stroids replay",
+            ));
+        }
+        if header[4] != REPLAY_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unsupported replay version {}", header[4]),
+            ));
+        }
+
+        let seed = u64::from_le_bytes(header[5..13].try_into().unwrap());
+        let ticks = u32::from_le_bytes(header[13..17].try_into().unwrap()) as usize;
+        let restarts = u32::from_le_bytes(header[17..21].try_into().unwrap()) as usize;
+
+        // the counts are only trusted once the file has that much in it
+        let expected = ticks as u64 + restarts as u64 * 4;
+        let found = file.metadata()?.len().saturating_sub(header.len() as u64);
+        if found != expected {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!(
+                    "replay of {} ticks and {} restarts should have {} bytes after the header, found {}",
+                    ticks, restarts, expected, found
+                ),
+            ));
+        }
+
+        let mut flags = vec![0; ticks];
+        file.read_exact(&mut flags)?;
+        let mut restart_ticks = vec![0; restarts * 4];
+        file.read_exact(&mut restart_ticks)?;
+
+        Ok(Replay {
+            seed,
+            inputs: flags.into_iter().map(input_from_flags).collect(),
+            restarts: restart_ticks
+                .chunks_exact(4)
+                .map(|tick| u32::from_le_bytes(tick.try_into().unwrap()))
+                .collect(),
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut file = io::BufWriter::new(std::fs::File::create(path)?);
+
+        file.write_all(REPLAY_MAGIC)?;
+        file.write_all(&[REPLAY_VERSION])?;
+        file.write_all(&self.seed.to_le_bytes())?;
+        file.write_all(&(self.inputs.len() as u32).to_le_bytes())?;
+        file.write_all(&(self.restarts.len() as u32).to_le_bytes())?;
+        for input in self.inputs.iter() {
+            file.write_all(&[flags_from_input(input)])?;
+        }
+        for tick in self.restarts.iter() {
+            file.write_all(&tick.to_le_bytes())?;
+        }
+
+        file.flush()
+    }
+}
+
+fn flags_from_input(input: &PlayerInput) -> u8 {
+    let mut flags = 0;
+    if input.thrust {
+        flags |= THRUST_BIT;
+    }
+    if input.rotate_left {
+        flags |= ROTATE_LEFT_BIT;
+    }
+    if input.rotate_right {
+        flags |= ROTATE_RIGHT_BIT;
+    }
+    if input.fire {
+        flags |= FIRE_BIT;
+    }
+    flags
+}
+
+fn input_from_flags(flags: u8) -> PlayerInput {
+    PlayerInput {
+        thrust: flags & THRUST_BIT != 0,
+        rotate_left: flags & ROTATE_LEFT_BIT != 0,
+        rotate_right: flags & ROTATE_RIGHT_BIT != 0,
+        fire: flags & FIRE_BIT != 0,
+    }
+}
+
+/// Records the input of every `InGame` tick and when the level is restarted, one replay per game. The first game of the session
+/// is written to `path` and each one after that next to it with its number added to the name,
+/// `replay-2.bin` and so on, on game over, on going back to the title and when the app exits.
+pub struct ReplayRecordPlugin {
+    pub path: PathBuf,
+}
+
+#[derive(Resource)]
+pub struct ReplayRecorder {
+    pub path: PathBuf,
+    pub replay: Replay,
+    /// Which game of the session is being recorded, counting from 1
+    pub game: u32,
+}
+
+impl ReplayRecorder {
+    /// Where the game being recorded is written.
+    pub fn game_path(&self) -> PathBuf {
+        if self.game <= 1 {
+            return self.path.clone();
+        }
+
+        let stem = self.path.file_stem().unwrap_or_default().to_string_lossy();
+        let name = match self.path.extension() {
+            Some(extension) => format!("{}-{}.{}", stem, self.game, extension.to_string_lossy()),
+            None => format!("{}-{}", stem, self.game),
+        };
+        self.path.with_file_name(name)
+    }
+
+    /// Writes out the game recorded so far and empties the recording, so a game is only saved
+    /// once and nothing is saved before a game has started.
+    pub fn save(&mut self) {
+        if self.replay.inputs.is_empty() {
+            return;
+        }
+
+        let path = self.game_path();
+        match self.replay.save(&path) {
+            Ok(()) => info!(
+                "saved {} ticks of replay to {}",
+                self.replay.inputs.len(),
+                path.display()
+            ),
+            Err(err) => error!("failed to save replay to {}: {}", path.display(), err),
+        }
+        self.replay.inputs.clear();
+        self.replay.restarts.clear();
+    }
+}
+
+impl Plugin for ReplayRecordPlugin {
+    fn build(&self, app: &mut App) {
+        let seed = app.world().resource::<GameSeed>().0;
+
+        app.insert_resource(ReplayRecorder {
+            path: self.path.clone(),
+            replay: Replay {
+                seed,
+                inputs: Vec::new(),
+                restarts: Vec::new(),
+            },
+            game: 0,
+        })
+        .add_systems(
+            OnEnter(GameState::NewGame),
+            start_recording.after(crate::seed_new_game),
+        )
+        .add_systems(
+            FixedUpdate,
+            record_input
+                .in_set(InGameSet::Input)
+                .before(crate::player_controls),
+        )
+        .add_systems(OnEnter(GameState::RestartLevel), record_restart)
+        .add_systems(OnEnter(GameState::GameOver), save_replay)
+        .add_systems(Last, save_replay.run_if(on_event::<AppExit>()));
+    }
+}
+
+/// Starts recording the game about to begin with its seed, after saving one left unsaved by
+/// quitting it from the pause menu.
+fn start_recording(seed: Res<GameSeed>, mut recorder: ResMut<ReplayRecorder>) {
+    recorder.save();
+    recorder.replay.seed = seed.0;
+    recorder.game += 1;
+}
+
+fn record_input(input: Res<PlayerInput>, mut recorder: ResMut<ReplayRecorder>) {
+    recorder.replay.inputs.push(*input);
+}
+
+/// Marks the level as restarted before the next tick.
+fn record_restart(mut recorder: ResMut<ReplayRecorder>) {
+    let tick = recorder.replay.inputs.len() as u32;
+    recorder.replay.restarts.push(tick);
+}
+
+fn save_replay(mut recorder: ResMut<ReplayRecorder>) {
+    recorder.save();
+}
+
+/// Plays a [`Replay`] back in place of the keyboard. The app has to be built with the replay's
+/// seed for the game to come out the same.
+///
+/// Menus are skipped automatically and the level is restarted wherever it was while recording. With a keyboard, [Space] pauses, [.] steps a single tick while
+/// paused and [F] toggles fast-forward.
+pub struct ReplayPlaybackPlugin {
+    pub replay: Replay,
+}
+
+#[derive(Resource)]
+pub struct ReplayPlayback {
+    pub replay: Replay,
+    pub tick: usize,
+    /// How many of the replay's restarts have been played back
+    restarts_played: usize,
+    step_requested: bool,
+}
+
+impl ReplayPlayback {
+    pub fn finished(&self) -> bool {
+        self.tick >= self.replay.inputs.len()
+    }
+}
+
+impl Plugin for ReplayPlaybackPlugin {
+    fn build(&self, app: &mut App) {
+        let seed = app.world().resource::<GameSeed>().0;
+        if seed != self.replay.seed {
+            warn!(
+                "replay was recorded with seed {} but the game is running with seed {}",
+                self.replay.seed, seed
+            );
+        }
+
+        app.insert_resource(ReplayPlayback {
+            replay: self.replay.clone(),
+            tick: 0,
+            restarts_played: 0,
+            step_requested: false,
+        })
+        .add_systems(
+            FixedUpdate,
+            (
+                // ahead of every gameplay set, so a restart stops the tick before any of it runs
+                playback_restart
+                    .before(InGameSet::Input)
+                    .run_if(in_state(GameState::InGame)),
+                playback_input
+                    .in_set(InGameSet::Input)
+                    .before(crate::player_controls),
+            ),
+        )
+        .add_systems(
+            Update,
+            skip_menu_during_playback
+                .run_if(in_state(GameState::NewGame).or_else(in_state(GameState::LevelComplete))),
+        )
+        .add_systems(
+            Update,
+            (
+                playback_controls.run_if(resource_exists::<ButtonInput<KeyCode>>),
+                step_playback,
+            )
+                .chain(),
+        );
+    }
+}
+
+fn playback_input(mut playback: ResMut<ReplayPlayback>, mut input: ResMut<PlayerInput>) {
+    *input = playback
+        .replay
+        .inputs
+        .get(playback.tick)
+        .copied()
+        .unwrap_or_default();
+
+    if playback.tick == playback.replay.inputs.len() {
+        info!("replay finished after {} ticks", playback.tick);
+    }
+    playback.tick += 1;
+}
+
+/// Restarts the level before the tick that followed a restart while recording.
+fn playback_restart(
+    mut playback: ResMut<ReplayPlayback>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let restart = playback
+        .replay
+        .restarts
+        .get(playback.restarts_played)
+        .copied();
+    if restart == Some(playback.tick as u32) {
+        playback.restarts_played += 1;
+        next_state.set(GameState::RestartLevel);
+    }
+}
+
+fn skip_menu_during_playback(
+    playback: Res<ReplayPlayback>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if !playback.finished() {
+        next_state.set(GameState::InGame);
+    }
+}
+
+fn playback_controls(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut playback: ResMut<ReplayPlayback>,
+    mut virtual_time: ResMut<Time<Virtual>>,
+) {
+    if keys.just_pressed(KeyCode::Space) {
+        if virtual_time.is_paused() {
+            virtual_time.unpause();
+        } else {
+            virtual_time.pause();
+        }
+    }
+
+    if keys.just_pressed(KeyCode::Period) && virtual_time.is_paused() {
+        playback.step_requested = true;
+    }
+
+    if keys.just_pressed(KeyCode::KeyF) {
+        let speed = if virtual_time.relative_speed() > 1. {
+            1.
+        } else {
+            FAST_FORWARD_SPEED
+        };
+        virtual_time.set_relative_speed(speed);
+    }
+}
+
+/// Runs a single fixed tick by hand, the fixed clock doesn't move on its own while paused.
+fn step_playback(world: &mut World) {
+    let mut playback = world.resource_mut::<ReplayPlayback>();
+    if !std::mem::take(&mut playback.step_requested) {
+        return;
+    }
+
+    let timestep = world.resource::<Time<Fixed>>().timestep();
+    world.resource_mut::<Time<Fixed>>().advance_by(timestep);
+    *world.resource_mut::<Time>() = world.resource::<Time<Fixed>>().as_generic();
+    world.run_schedule(FixedMain);
+    *world.resource_mut::<Time>() = world.resource::<Time<Virtual>>().as_generic();
+}
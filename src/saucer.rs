@@ -0,0 +1,442 @@
+use crate::check_wrapped_collision;
+use crate::constants::*;
+use crate::physics::Arena;
+use crate::physics::Heading;
+use crate::physics::Velocity;
+use crate::ArenaBoundary;
+use crate::CollisionHulls;
+use crate::Despawning;
+use crate::GameAssets;
+use crate::HullAsset;
+use crate::InGameSet;
+use crate::Invulnerable;
+use crate::Level;
+use crate::PhysicsSet;
+use crate::Player;
+use crate::PlayerKilledEvent;
+use crate::Playing;
+use crate::Projectile;
+use crate::TravelDistance;
+use crate::Tuning;
+use crate::WorldHull;
+use bevy::prelude::*;
+use std::f32::consts::TAU;
+
+use bevy_prng::WyRand;
+use bevy_rand::prelude::EntropyComponent;
+use rand_core::RngCore;
+
+const SAUCER_COLOR: Color = Color::WHITE;
+const SAUCER_PROJECTILE_COLOR: Color = Color::WHITE;
+
+/// Flying saucers that cross the screen from one side to the other on a wandering path, shooting
+/// at the ship. Large saucers fire in any direction, small ones lead the ship. Only one saucer is
+/// out at a time and they turn up more often, and more of them small, as the level goes up.
+pub struct SaucerPlugin;
+
+impl Plugin for SaucerPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(SaucerSpawnTimer(Timer::default()))
+            .add_event::<SaucerFiredEvent>()
+            .add_event::<SaucerDestroyedEvent>()
+            .add_systems(OnEnter(Playing), reset_saucer_spawn_timer)
+            .add_systems(
+                FixedUpdate,
+                (
+                    saucer_ai.in_set(InGameSet::Input),
+                    (saucer_spawner, saucer_projectile_spawner).in_set(InGameSet::Spawn),
+                    // the projectiles collision_system used up are marked Despawning by then, so
+                    // none of them shoots a saucer down as well
+                    (apply_deferred, saucer_collision_system)
+                        .chain()
+                        .in_set(PhysicsSet::Collision)
+                        .after(crate::collision_system),
+                ),
+            );
+    }
+}
+
+/// RNG stream for when, where and what kind of saucer turns up, its path and its aim.
+#[derive(Resource, Deref, DerefMut)]
+pub struct SaucerRng(pub EntropyComponent<WyRand>);
+
+/// Counts down to the next saucer, only while there isn't one out already.
+#[derive(Resource)]
+pub struct SaucerSpawnTimer(pub Timer);
+
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SaucerSize {
+    Small,
+    Large,
+}
+
+#[derive(Component)]
+pub struct Saucer;
+
+#[derive(Component)]
+pub struct SaucerAi {
+    /// 1 flying right, -1 flying left
+    direction: f32,
+    /// Horizontal distance flown, the saucer leaves once it has crossed the arena
+    travelled: f32,
+    direction_timer: Timer,
+    fire_timer: Timer,
+}
+
+#[derive(Component)]
+pub struct SaucerProjectile;
+
+/// A shot from a saucer, from the position at the heading.
+#[derive(Event)]
+pub struct SaucerFiredEvent(pub Vec2, pub Heading);
+
+#[derive(Event)]
+pub struct SaucerDestroyedEvent(pub SaucerSize);
+
+#[derive(Bundle)]
+struct SaucerBundle {
+    saucer: Saucer,
+    size: SaucerSize,
+    ai: SaucerAi,
+    velocity: Velocity,
+    heading: Heading,
+    sprite_bundle: SpriteBundle,
+    hull: Handle<HullAsset>,
+    world_hull: WorldHull,
+    boundary: ArenaBoundary,
+}
+
+#[derive(Bundle)]
+struct SaucerProjectileBundle {
+    projectile: SaucerProjectile,
+    heading: Heading,
+    velocity: Velocity,
+    travel_distance: TravelDistance,
+    sprite_bundle: SpriteBundle,
+    hull: Handle<HullAsset>,
+    world_hull: WorldHull,
+    boundary: ArenaBoundary,
+}
+
+impl SaucerBundle {
+    pub fn new(
+        size: SaucerSize,
+        position: Vec2,
+        direction: f32,
+        game_assets: &GameAssets,
+        collision_hulls: &CollisionHulls,
+        tuning: &Tuning,
+    ) -> SaucerBundle {
+        let saucers = &tuning.saucers;
+        let fire_interval = match size {
+            SaucerSize::Small => saucers.small_fire_interval,
+            SaucerSize::Large => saucers.large_fire_interval,
+        };
+
+        SaucerBundle {
+            saucer: Saucer,
+            size,
+            ai: SaucerAi {
+                direction,
+                travelled: 0.,
+                direction_timer: Timer::from_seconds(
+                    saucers.direction_change_interval,
+                    TimerMode::Repeating,
+                ),
+                fire_timer: Timer::from_seconds(fire_interval, TimerMode::Repeating),
+            },
+            velocity: Velocity(Vec2::new(direction * saucer_speed(size, tuning), 0.)),
+            heading: Heading(0.),
+            hull: match size {
+                SaucerSize::Small => collision_hulls.saucer_sm.clone(),
+                SaucerSize::Large => collision_hulls.saucer_lg.clone(),
+            },
+            world_hull: WorldHull::default(),
+            boundary: ArenaBoundary::default(),
+            sprite_bundle: SpriteBundle {
+                sprite: Sprite {
+                    color: SAUCER_COLOR,
+                    custom_size: match size {
+                        SaucerSize::Small => Some(saucers.small_size),
+                        SaucerSize::Large => Some(saucers.large_size),
+                    },
+                    ..default()
+                },
+                texture: match size {
+                    SaucerSize::Small => game_assets.saucer_sm.clone(),
+                    SaucerSize::Large => game_assets.saucer_lg.clone(),
+                },
+                transform: Transform {
+                    translation: position.extend(1.0),
+                    ..default()
+                },
+                ..default()
+            },
+        }
+    }
+}
+
+impl SaucerProjectileBundle {
+    pub fn new(
+        start: Vec2,
+        heading: f32,
+        collision_hulls: &CollisionHulls,
+        tuning: &Tuning,
+    ) -> SaucerProjectileBundle {
+        let heading_vec = Vec2::new(heading.cos(), heading.sin());
+        SaucerProjectileBundle {
+            projectile: SaucerProjectile,
+            heading: Heading(heading),
+            velocity: Velocity(heading_vec * tuning.saucers.projectile_speed()),
+            travel_distance: TravelDistance::new(tuning.saucers.projectile_range),
+            hull: collision_hulls.projectile.clone(),
+            world_hull: WorldHull::default(),
+            boundary: ArenaBoundary::default(),
+            sprite_bundle: SpriteBundle {
+                sprite: Sprite {
+                    color: SAUCER_PROJECTILE_COLOR,
+                    ..default()
+                },
+                transform: Transform {
+                    translation: start.extend(1.0),
+                    scale: Vec3::new(4., 4., 1.),
+                    ..default()
+                },
+                ..default()
+            },
+        }
+    }
+}
+
+fn saucer_speed(size: SaucerSize, tuning: &Tuning) -> f32 {
+    match size {
+        SaucerSize::Small => VIEW_WIDTH / tuning.saucers.small_screen_cross_time,
+        SaucerSize::Large => VIEW_WIDTH / tuning.saucers.large_screen_cross_time,
+    }
+}
+
+/// Uniform in `[0, 1)`.
+fn random_unit(rng: &mut SaucerRng) -> f32 {
+    (rng.next_u32() >> 8) as f32 / (1 << 24) as f32
+}
+
+/// The heading to fire at `speed` from `from` to meet a target moving at a steady velocity,
+/// or straight at the target if the shot can't catch it.
+pub fn intercept_heading(from: Vec2, target: Vec2, target_velocity: Vec2, speed: f32) -> f32 {
+    let offset = target - from;
+
+    // where the shot and the target meet, |offset + target_velocity * t| = speed * t
+    let a = target_velocity.length_squared() - speed * speed;
+    let b = 2. * offset.dot(target_velocity);
+    let c = offset.length_squared();
+
+    let time = if a.abs() < f32::EPSILON {
+        -c / b
+    } else {
+        let discriminant = b * b - 4. * a * c;
+        if discriminant < 0. {
+            -1.
+        } else {
+            let root = discriminant.sqrt();
+            let t1 = (-b - root) / (2. * a);
+            let t2 = (-b + root) / (2. * a);
+            match (t1 > 0., t2 > 0.) {
+                (true, true) => t1.min(t2),
+                (true, false) => t1,
+                (false, true) => t2,
+                (false, false) => -1.,
+            }
+        }
+    };
+
+    let aim = if time.is_finite() && time > 0. {
+        offset + target_velocity * time
+    } else {
+        offset
+    };
+    aim.y.atan2(aim.x)
+}
+
+pub fn reset_saucer_spawn_timer(
+    mut timer: ResMut<SaucerSpawnTimer>,
+    level_query: Query<&Level>,
+    tuning: Res<Tuning>,
+) {
+    let level = level_query.single();
+    timer.0 = Timer::from_seconds(
+        tuning.saucers.spawn_interval_for_level(level.0),
+        TimerMode::Once,
+    );
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn saucer_spawner(
+    mut commands: Commands,
+    mut timer: ResMut<SaucerSpawnTimer>,
+    mut rng: ResMut<SaucerRng>,
+    saucer_query: Query<(), With<Saucer>>,
+    level_query: Query<&Level>,
+    arena: Res<Arena>,
+    game_assets: Res<GameAssets>,
+    collision_hulls: Res<CollisionHulls>,
+    tuning: Res<Tuning>,
+    time: Res<Time>,
+) {
+    if !saucer_query.is_empty() || !timer.0.tick(time.delta()).finished() {
+        return;
+    }
+
+    let level = level_query.single();
+    timer.0 = Timer::from_seconds(
+        tuning.saucers.spawn_interval_for_level(level.0),
+        TimerMode::Once,
+    );
+
+    let size = if random_unit(&mut rng) < tuning.saucers.small_chance_for_level(level.0) {
+        SaucerSize::Small
+    } else {
+        SaucerSize::Large
+    };
+
+    // in from one side at any height away from the very top and bottom
+    let direction = if rng.next_u32() & 1 == 0 { 1. } else { -1. };
+    let x = -direction * arena.size.x / 2.;
+    let y = (random_unit(&mut rng) - 0.5) * arena.size.y * 0.8;
+
+    commands.spawn(SaucerBundle::new(
+        size,
+        Vec2::new(x, y),
+        direction,
+        &game_assets,
+        &collision_hulls,
+        &tuning,
+    ));
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn saucer_ai(
+    mut commands: Commands,
+    mut saucer_query: Query<
+        (
+            Entity,
+            &SaucerSize,
+            &mut SaucerAi,
+            &mut Velocity,
+            &Transform,
+        ),
+        (With<Saucer>, Without<Despawning>),
+    >,
+    player_query: Query<(&Transform, &Velocity), (With<Player>, Without<Saucer>)>,
+    mut rng: ResMut<SaucerRng>,
+    arena: Res<Arena>,
+    tuning: Res<Tuning>,
+    time: Res<Time>,
+    mut ev_fire: EventWriter<SaucerFiredEvent>,
+) {
+    for (entity, size, mut ai, mut velocity, transform) in saucer_query.iter_mut() {
+        ai.travelled += velocity.0.x.abs() * time.delta_seconds();
+        if ai.travelled >= arena.size.x {
+            commands.entity(entity).insert(Despawning);
+            continue;
+        }
+
+        // wander: keep going across, but straight, angled up or angled down
+        if ai.direction_timer.tick(time.delta()).just_finished() {
+            let speed = saucer_speed(*size, &tuning);
+            let vertical = match rng.next_u32() % 3 {
+                0 => -0.5,
+                1 => 0.,
+                _ => 0.5,
+            };
+            velocity.0 = Vec2::new(ai.direction * speed, vertical * speed);
+        }
+
+        if !ai.fire_timer.tick(time.delta()).just_finished() {
+            continue;
+        }
+
+        let position = transform.translation.truncate();
+        let heading = match (size, player_query.get_single()) {
+            (SaucerSize::Small, Ok((player_transform, player_velocity))) => {
+                let miss = (random_unit(&mut rng) * 2. - 1.) * tuning.saucers.small_aim_error;
+                intercept_heading(
+                    position,
+                    player_transform.translation.truncate(),
+                    player_velocity.0,
+                    tuning.saucers.projectile_speed(),
+                ) + miss
+            }
+            _ => random_unit(&mut rng) * TAU,
+        };
+
+        ev_fire.send(SaucerFiredEvent(position, Heading(heading)));
+    }
+}
+
+pub fn saucer_projectile_spawner(
+    mut commands: Commands,
+    mut ev_fire: EventReader<SaucerFiredEvent>,
+    collision_hulls: Res<CollisionHulls>,
+    tuning: Res<Tuning>,
+) {
+    for ev in ev_fire.read() {
+        commands.spawn(SaucerProjectileBundle::new(
+            ev.0,
+            ev.1 .0,
+            &collision_hulls,
+            &tuning,
+        ));
+    }
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn saucer_collision_system(
+    mut commands: Commands,
+    arena: Res<Arena>,
+    player_query: Query<&WorldHull, (With<Player>, Without<Invulnerable>, Without<Despawning>)>,
+    projectile_query: Query<(Entity, &WorldHull), (With<Projectile>, Without<Despawning>)>,
+    saucer_query: Query<(Entity, &SaucerSize, &WorldHull), (With<Saucer>, Without<Despawning>)>,
+    saucer_projectile_query: Query<
+        (Entity, &WorldHull),
+        (With<SaucerProjectile>, Without<Despawning>),
+    >,
+    mut saucer_destroyed_event: EventWriter<SaucerDestroyedEvent>,
+    mut player_killed_event: EventWriter<PlayerKilledEvent>,
+) {
+    let player = player_query.get_single().ok();
+    let mut player_killed = false;
+
+    for (saucer, saucer_size, saucer_hull) in saucer_query.iter() {
+        let shot_down = projectile_query.iter().find(|(_, projectile_hull)| {
+            check_wrapped_collision(projectile_hull, saucer_hull, arena.size).is_some()
+        });
+
+        let rammed = player.is_some_and(|player_hull| {
+            check_wrapped_collision(player_hull, saucer_hull, arena.size).is_some()
+        });
+
+        if let Some((projectile, _)) = shot_down {
+            commands.entity(projectile).insert(Despawning);
+        }
+        if rammed {
+            player_killed = true;
+        }
+        if shot_down.is_some() || rammed {
+            commands.entity(saucer).insert(Despawning);
+            saucer_destroyed_event.send(SaucerDestroyedEvent(*saucer_size));
+        }
+    }
+
+    if let Some(player_hull) = player {
+        for (projectile, projectile_hull) in saucer_projectile_query.iter() {
+            if check_wrapped_collision(projectile_hull, player_hull, arena.size).is_some() {
+                commands.entity(projectile).insert(Despawning);
+                player_killed = true;
+            }
+        }
+    }
+
+    if player_killed {
+        player_killed_event.send(PlayerKilledEvent);
+    }
+}
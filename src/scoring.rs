@@ -1,6 +1,19 @@
 use bevy::prelude::*;
 
-use crate::{AsteroidDestroyedEvent, LevelUpEvent, Lives};
+use crate::{
+    AsteroidDestroyedEvent, GameState, InGameSet, LevelUpEvent, Lives, SaucerDestroyedEvent,
+    SaucerSize, Tuning,
+};
+
+pub struct ScoringPlugin;
+
+impl Plugin for ScoringPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup_score)
+            .add_systems(OnEnter(GameState::NewGame), reset_score)
+            .add_systems(FixedUpdate, update_score_listener.in_set(InGameSet::Score));
+    }
+}
 
 #[derive(Resource, Clone)]
 pub struct Score(pub u64);
@@ -15,24 +28,28 @@ pub fn reset_score(mut score: ResMut<Score>) {
     score.0 = 0;
 }
 
-const LARGE_ASTEROID_VALUE: u64 = 100;
-const MEDIUM_ASTEROID_VALUE: u64 = 50;
-const SMALL_ASTEROID_VALUE: u64 = 25;
-pub const LIFE_BONUS: u64 = 1000;
-
 pub fn update_score_listener(
     mut asteroid_destroyed_event: EventReader<AsteroidDestroyedEvent>,
+    mut saucer_destroyed_event: EventReader<SaucerDestroyedEvent>,
     mut level_up_event: EventReader<LevelUpEvent>,
     mut score: ResMut<Score>,
     lives_query: Query<&Lives>,
+    tuning: Res<Tuning>,
 ) {
     for ev in asteroid_destroyed_event.read() {
         let (_, _, _, size) = (ev.0, ev.1, ev.2, ev.3);
 
         score.0 += match size {
-            crate::AsteroidSize::Small => SMALL_ASTEROID_VALUE,
-            crate::AsteroidSize::Medium => MEDIUM_ASTEROID_VALUE,
-            crate::AsteroidSize::Large => LARGE_ASTEROID_VALUE,
+            crate::AsteroidSize::Small => tuning.scoring.small_asteroid,
+            crate::AsteroidSize::Medium => tuning.scoring.medium_asteroid,
+            crate::AsteroidSize::Large => tuning.scoring.large_asteroid,
+        };
+    }
+
+    for ev in saucer_destroyed_event.read() {
+        score.0 += match ev.0 {
+            SaucerSize::Small => tuning.scoring.small_saucer,
+            SaucerSize::Large => tuning.scoring.large_saucer,
         };
     }
 
@@ -40,6 +57,6 @@ pub fn update_score_listener(
         let level = ev.0 .0;
         let lives = lives_query.single();
 
-        score.0 += lives.0 as u64 * (LIFE_BONUS * level as u64);
+        score.0 += lives.0 as u64 * (tuning.scoring.life_bonus * level as u64);
     }
 }
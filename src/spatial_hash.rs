@@ -0,0 +1,96 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+/// Broad phase for collision detection: a uniform grid of square cells, each listing the entities
+/// whose bounding circle touches it. It's rebuilt from scratch every `FixedUpdate` tick, then only
+/// entities sharing a cell with something are worth handing to `check_world_hull_collision`.
+///
+/// In a wrapping arena a circle hanging off an edge is filed and looked up again at the opposite
+/// edge, so neighbours across the seam still share cells.
+#[derive(Resource)]
+pub struct SpatialHash {
+    cell_size: f32,
+    cells: HashMap<IVec2, Vec<Entity>>,
+    /// Size of the arena centred on the origin whose edges wrap around, if they do
+    wrap: Option<Vec2>,
+}
+
+impl SpatialHash {
+    pub fn new(cell_size: f32) -> SpatialHash {
+        SpatialHash {
+            cell_size,
+            cells: HashMap::default(),
+            wrap: None,
+        }
+    }
+
+    /// Wraps the grid around an arena `size` big and centred on the origin, or stops wrapping it.
+    /// Takes effect for entities inserted after it.
+    pub fn set_wrap(&mut self, size: Option<Vec2>) {
+        self.wrap = size;
+    }
+
+    /// Empties every cell, keeping their allocations for the next rebuild.
+    pub fn clear(&mut self) {
+        for entities in self.cells.values_mut() {
+            entities.clear();
+        }
+    }
+
+    /// Adds `entity` to every cell its bounding circle overlaps.
+    pub fn insert(&mut self, entity: Entity, position: Vec2, radius: f32) {
+        for image in self.images(position, radius) {
+            let (min, max) = self.cell_range(image, radius);
+            for y in min.y..=max.y {
+                for x in min.x..=max.x {
+                    self.cells.entry(IVec2::new(x, y)).or_default().push(entity);
+                }
+            }
+        }
+    }
+
+    /// Fills `found` with every entity sharing a cell with the circle, each once and in entity
+    /// order so the results don't depend on how the grid was filled.
+    pub fn query(&self, position: Vec2, radius: f32, found: &mut Vec<Entity>) {
+        found.clear();
+
+        for image in self.images(position, radius) {
+            let (min, max) = self.cell_range(image, radius);
+            for y in min.y..=max.y {
+                for x in min.x..=max.x {
+                    if let Some(entities) = self.cells.get(&IVec2::new(x, y)) {
+                        found.extend_from_slice(entities);
+                    }
+                }
+            }
+        }
+
+        // anything bigger than a cell, or across a seam, is listed in several of them
+        found.sort_unstable();
+        found.dedup();
+    }
+
+    /// The circle's position, then wherever it also shows up across each arena edge or corner
+    /// it hangs over.
+    fn images(&self, position: Vec2, radius: f32) -> impl Iterator<Item = Vec2> {
+        let half = self.wrap.map_or(Vec2::INFINITY, |size| size / 2.);
+        let shifts = move |centre: f32, half: f32| {
+            [
+                Some(0.),
+                (centre - radius < -half).then_some(2. * half),
+                (centre + radius > half).then_some(-2. * half),
+            ]
+            .into_iter()
+            .flatten()
+        };
+
+        shifts(position.x, half.x)
+            .flat_map(move |x| shifts(position.y, half.y).map(move |y| position + Vec2::new(x, y)))
+    }
+
+    fn cell_range(&self, position: Vec2, radius: f32) -> (IVec2, IVec2) {
+        let min = ((position - radius) / self.cell_size).floor().as_ivec2();
+        let max = ((position + radius) / self.cell_size).floor().as_ivec2();
+        (min, max)
+    }
+}
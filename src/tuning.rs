@@ -0,0 +1,666 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use bevy::{
+    asset::{
+        io::file::FileAssetReader, io::Reader, AssetLoadFailedEvent, AssetLoader, AsyncReadExt,
+        LoadContext,
+    },
+    prelude::*,
+};
+use serde::Deserialize;
+
+use crate::constants::*;
+use crate::Boundaries;
+
+/// Loads the gameplay numbers from a RON file into the [`Tuning`] resource, so the feel of the
+/// game can be changed without recompiling. `path` is relative to the `assets` directory. Without
+/// a `path`, or if the file is missing, the built in defaults are used. A file that fails to parse
+/// or validate is reported and ignored.
+///
+/// The file is read straight away so the values are in place before anything spawns. When the app
+/// has an `AssetPlugin` it's also loaded as a [`TuningAsset`], so with file watching turned on an
+/// edit is applied while the game is running. A bad edit leaves the previous values in effect and
+/// puts the reason in [`TuningNotice`].
+pub struct TuningPlugin {
+    pub path: Option<PathBuf>,
+}
+
+impl Default for TuningPlugin {
+    fn default() -> Self {
+        TuningPlugin {
+            path: Some(PathBuf::from(TUNING_FILE)),
+        }
+    }
+}
+
+impl Plugin for TuningPlugin {
+    fn build(&self, app: &mut App) {
+        let tuning = match &self.path {
+            Some(path) => {
+                let path = FileAssetReader::get_base_path().join("assets").join(path);
+                Tuning::load(&path).unwrap_or_else(|err| {
+                    error!(
+                        "failed to load tuning from {}, using defaults: {}",
+                        path.display(),
+                        err
+                    );
+                    Tuning::default()
+                })
+            }
+            None => Tuning::default(),
+        };
+
+        app.insert_resource(tuning).init_resource::<TuningNotice>();
+
+        if let (Some(path), true) = (&self.path, app.is_plugin_added::<AssetPlugin>()) {
+            let handle = app
+                .init_asset::<TuningAsset>()
+                .register_asset_loader(TuningLoader)
+                .world()
+                .resource::<AssetServer>()
+                .load(path.clone());
+
+            app.insert_resource(TuningHandle(handle))
+                .add_systems(Update, apply_tuning_changes);
+        }
+    }
+}
+
+/// The tuning file as loaded by the asset server, copied into [`Tuning`] whenever it changes.
+#[derive(Asset, TypePath, Clone, Debug)]
+pub struct TuningAsset(pub Tuning);
+
+#[derive(Resource)]
+pub struct TuningHandle(pub Handle<TuningAsset>);
+
+/// Why the last edit of the tuning file was rejected, cleared once a good edit is applied.
+#[derive(Resource, Default, Clone, Debug)]
+pub struct TuningNotice(pub Option<String>);
+
+#[derive(Default)]
+pub struct TuningLoader;
+
+impl AssetLoader for TuningLoader {
+    type Asset = TuningAsset;
+    type Settings = ();
+    type Error = TuningError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> Result<TuningAsset, TuningError> {
+        let mut contents = String::new();
+        reader
+            .read_to_string(&mut contents)
+            .await
+            .map_err(TuningError::Io)?;
+
+        Tuning::from_ron(&contents).map(TuningAsset)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["ron"]
+    }
+}
+
+fn apply_tuning_changes(
+    mut asset_events: EventReader<AssetEvent<TuningAsset>>,
+    mut failed_events: EventReader<AssetLoadFailedEvent<TuningAsset>>,
+    assets: Res<Assets<TuningAsset>>,
+    handle: Res<TuningHandle>,
+    mut tuning: ResMut<Tuning>,
+    mut notice: ResMut<TuningNotice>,
+) {
+    for event in asset_events.read() {
+        let (AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id }) = event
+        else {
+            continue;
+        };
+        if *id != handle.0.id() {
+            continue;
+        }
+
+        if let Some(asset) = assets.get(*id) {
+            if tuning.set_if_neq(asset.0.clone()) {
+                info!("applied tuning changes");
+            }
+            notice.0 = None;
+        }
+    }
+
+    for event in failed_events.read() {
+        if event.id != handle.0.id() {
+            continue;
+        }
+
+        notice.0 = Some(format!(
+            "Tuning not applied, keeping previous values\n{}",
+            event.error
+        ));
+    }
+}
+
+/// Every number that shapes how the game plays. Fields left out of the file keep their default.
+#[derive(Resource, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Tuning {
+    pub ship: ShipTuning,
+    pub projectile: ProjectileTuning,
+    pub asteroids: AsteroidTuning,
+    pub saucers: SaucerTuning,
+    pub scoring: ScoringTuning,
+    pub boundaries: BoundaryTuning,
+    pub world: WorldTuning,
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ShipTuning {
+    /// Seconds to cross the width of the screen at top speed
+    pub screen_cross_time: f32,
+    /// Seconds of thrust to reach top speed from a standstill
+    pub acceleration_time: f32,
+    /// Seconds for a full turn
+    pub rotation_time: f32,
+    pub size: Vec2,
+    pub lives: u8,
+    /// Seconds after a death before the ship comes back
+    pub respawn_delay: f32,
+    /// Seconds the ship can't be hit after coming back
+    pub invulnerability_time: f32,
+    /// The ship only comes back once no asteroid is this close to the centre
+    pub respawn_clear_radius: f32,
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProjectileTuning {
+    /// Muzzle speed as a multiple of the ship's top speed, added to the ship's velocity
+    pub speed: f32,
+    /// Distance travelled before the projectile disappears
+    pub range: f32,
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct AsteroidTuning {
+    /// Asteroids in the field before the level is added on
+    pub base_count: u32,
+    pub count_per_level: u32,
+    /// Seconds for the fastest possible asteroid to cross the width of the screen
+    pub screen_cross_time: f32,
+    /// Seconds for a full turn of the fastest spinning asteroid
+    pub rotation_time: f32,
+    /// No asteroid in a new field starts closer than this to the centre
+    pub safe_radius: f32,
+    pub large_size: Vec2,
+    pub medium_size: Vec2,
+    pub small_size: Vec2,
+    /// Asteroids bounce off each other instead of passing through
+    pub collide: bool,
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct SaucerTuning {
+    /// Seconds between one saucer leaving and the next arriving on level 1
+    pub spawn_interval: f32,
+    /// The interval is multiplied by this for every level after the first
+    pub spawn_interval_scale: f32,
+    /// The interval never drops below this however high the level
+    pub min_spawn_interval: f32,
+    /// Chance a saucer is a small one on level 1, 0 to 1
+    pub small_chance: f32,
+    /// Added to the small saucer chance for every level after the first
+    pub small_chance_per_level: f32,
+    /// Seconds for a large saucer to cross the width of the screen
+    pub large_screen_cross_time: f32,
+    /// Seconds for a small saucer to cross the width of the screen
+    pub small_screen_cross_time: f32,
+    /// Seconds between changes of direction
+    pub direction_change_interval: f32,
+    /// Seconds between shots from a large saucer
+    pub large_fire_interval: f32,
+    /// Seconds between shots from a small saucer
+    pub small_fire_interval: f32,
+    /// Largest miss in radians of a small saucer's aimed shots
+    pub small_aim_error: f32,
+    /// Seconds for a saucer shot to cross the width of the screen
+    pub projectile_screen_cross_time: f32,
+    /// Distance a saucer shot travels before disappearing
+    pub projectile_range: f32,
+    pub large_size: Vec2,
+    pub small_size: Vec2,
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScoringTuning {
+    pub large_asteroid: u64,
+    pub medium_asteroid: u64,
+    pub small_asteroid: u64,
+    pub large_saucer: u64,
+    pub small_saucer: u64,
+    /// Awarded per remaining life per level completed
+    pub life_bonus: u64,
+}
+
+/// What each kind of entity does at the edges of the arena, level by level.
+#[derive(Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct BoundaryTuning {
+    /// The modes from level 1
+    pub modes: Boundaries,
+    /// From each level listed until the next, its modes replace the ones above, with any left out
+    /// wrapping
+    pub levels: BTreeMap<u32, Boundaries>,
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct WorldTuning {
+    /// Size of the arena in screens across and up, bigger than one screen scrolls with the ship
+    pub screens: Vec2,
+    /// Seconds for the camera to close most of the distance to where it should be, 0 to keep it
+    /// right on it
+    pub camera_lag: f32,
+    /// The camera looks ahead of the ship by the distance it covers in this many seconds
+    pub camera_look_ahead: f32,
+}
+
+impl Default for ShipTuning {
+    fn default() -> Self {
+        ShipTuning {
+            screen_cross_time: 1.5,
+            acceleration_time: 1.0,
+            rotation_time: 0.75,
+            size: Vec2::new(64., 32.),
+            lives: 5,
+            respawn_delay: 2.0,
+            invulnerability_time: 3.0,
+            respawn_clear_radius: 120.,
+        }
+    }
+}
+
+impl Default for ProjectileTuning {
+    fn default() -> Self {
+        ProjectileTuning {
+            speed: 1.0,
+            range: VIEW_WIDTH,
+        }
+    }
+}
+
+impl Default for AsteroidTuning {
+    fn default() -> Self {
+        AsteroidTuning {
+            base_count: 1,
+            count_per_level: 2,
+            screen_cross_time: 12.0,
+            rotation_time: 2.0,
+            safe_radius: 200.,
+            large_size: Vec2::new(128., 128.),
+            medium_size: Vec2::new(64., 64.),
+            small_size: Vec2::new(32., 32.),
+            collide: false,
+        }
+    }
+}
+
+impl Default for SaucerTuning {
+    fn default() -> Self {
+        SaucerTuning {
+            spawn_interval: 20.,
+            spawn_interval_scale: 0.85,
+            min_spawn_interval: 6.,
+            small_chance: 0.2,
+            small_chance_per_level: 0.1,
+            large_screen_cross_time: 8.,
+            small_screen_cross_time: 5.,
+            direction_change_interval: 1.5,
+            large_fire_interval: 1.5,
+            small_fire_interval: 1.,
+            small_aim_error: 0.1,
+            projectile_screen_cross_time: 2.,
+            projectile_range: 700.,
+            large_size: Vec2::new(64., 32.),
+            small_size: Vec2::new(32., 16.),
+        }
+    }
+}
+
+impl Default for ScoringTuning {
+    fn default() -> Self {
+        ScoringTuning {
+            large_asteroid: 100,
+            medium_asteroid: 50,
+            small_asteroid: 25,
+            large_saucer: 200,
+            small_saucer: 1000,
+            life_bonus: 1000,
+        }
+    }
+}
+
+impl ShipTuning {
+    pub fn max_speed(&self) -> f32 {
+        VIEW_WIDTH / self.screen_cross_time
+    }
+
+    pub fn thrust_power(&self) -> f32 {
+        self.max_speed() / self.acceleration_time
+    }
+
+    pub fn rotation_speed(&self) -> f32 {
+        std::f32::consts::TAU / self.rotation_time
+    }
+}
+
+impl AsteroidTuning {
+    pub fn max_speed(&self) -> f32 {
+        VIEW_WIDTH / self.screen_cross_time
+    }
+
+    pub fn max_rotation_speed(&self) -> f32 {
+        std::f32::consts::TAU / self.rotation_time
+    }
+
+    /// Asteroids in the field on `level`, never more than [`MAX_ASTEROID_COUNT`].
+    pub fn count_for_level(&self, level: u32) -> u32 {
+        self.count_per_level
+            .saturating_mul(level)
+            .saturating_add(self.base_count)
+            .min(MAX_ASTEROID_COUNT)
+    }
+}
+
+impl SaucerTuning {
+    pub fn spawn_interval_for_level(&self, level: u32) -> f32 {
+        let scaled = self.spawn_interval
+            * self
+                .spawn_interval_scale
+                .powi(level.saturating_sub(1) as i32);
+        scaled.max(self.min_spawn_interval)
+    }
+
+    pub fn small_chance_for_level(&self, level: u32) -> f32 {
+        (self.small_chance + self.small_chance_per_level * level.saturating_sub(1) as f32)
+            .clamp(0., 1.)
+    }
+
+    pub fn projectile_speed(&self) -> f32 {
+        VIEW_WIDTH / self.projectile_screen_cross_time
+    }
+}
+
+impl Default for WorldTuning {
+    fn default() -> Self {
+        WorldTuning {
+            screens: Vec2::ONE,
+            camera_lag: 0.3,
+            camera_look_ahead: 0.4,
+        }
+    }
+}
+
+impl WorldTuning {
+    pub fn size(&self) -> Vec2 {
+        Vec2::new(VIEW_WIDTH, VIEW_HEIGHT) * self.screens
+    }
+}
+
+impl BoundaryTuning {
+    pub fn for_level(&self, level: u32) -> Boundaries {
+        self.levels
+            .range(..=level)
+            .next_back()
+            .map_or(self.modes, |(_, modes)| *modes)
+    }
+}
+
+impl Tuning {
+    pub fn load(path: &Path) -> Result<Tuning, TuningError> {
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                info!("no tuning file at {}, using defaults", path.display());
+                return Ok(Tuning::default());
+            }
+            Err(err) => return Err(TuningError::Io(err)),
+        };
+
+        Tuning::from_ron(&contents)
+    }
+
+    pub fn from_ron(contents: &str) -> Result<Tuning, TuningError> {
+        let tuning: Tuning = ron::de::from_str(contents).map_err(TuningError::Parse)?;
+        tuning.validate()?;
+        Ok(tuning)
+    }
+
+    /// Checks every value is one the game can actually run with, collecting all the problems
+    /// rather than stopping at the first.
+    pub fn validate(&self) -> Result<(), TuningError> {
+        let mut problems = Vec::new();
+
+        positive(
+            &mut problems,
+            "ship.screen_cross_time",
+            self.ship.screen_cross_time,
+        );
+        positive(
+            &mut problems,
+            "ship.acceleration_time",
+            self.ship.acceleration_time,
+        );
+        positive(&mut problems, "ship.rotation_time", self.ship.rotation_time);
+        positive_size(&mut problems, "ship.size", self.ship.size);
+        if self.ship.lives == 0 {
+            problems.push("ship.lives must be at least 1".to_string());
+        }
+        non_negative(&mut problems, "ship.respawn_delay", self.ship.respawn_delay);
+        non_negative(
+            &mut problems,
+            "ship.invulnerability_time",
+            self.ship.invulnerability_time,
+        );
+        positive(
+            &mut problems,
+            "ship.respawn_clear_radius",
+            self.ship.respawn_clear_radius,
+        );
+
+        positive(&mut problems, "projectile.speed", self.projectile.speed);
+        positive(&mut problems, "projectile.range", self.projectile.range);
+
+        if self.asteroids.count_for_level(1) == 0 {
+            problems.push(
+                "asteroids.base_count and asteroids.count_per_level can't both be 0".to_string(),
+            );
+        }
+        for (name, count) in [
+            ("asteroids.base_count", self.asteroids.base_count),
+            ("asteroids.count_per_level", self.asteroids.count_per_level),
+        ] {
+            if count > MAX_ASTEROID_COUNT {
+                problems.push(format!(
+                    "{} must be at most {}, got {}",
+                    name, MAX_ASTEROID_COUNT, count
+                ));
+            }
+        }
+        positive(
+            &mut problems,
+            "asteroids.screen_cross_time",
+            self.asteroids.screen_cross_time,
+        );
+        positive(
+            &mut problems,
+            "asteroids.rotation_time",
+            self.asteroids.rotation_time,
+        );
+        // asteroids are placed between the safe radius and the nearest screen edge
+        let max_safe_radius = MAX_X_POSITION.min(MAX_Y_POSITION);
+        if !(0. ..max_safe_radius).contains(&self.asteroids.safe_radius) {
+            problems.push(format!(
+                "asteroids.safe_radius must be at least 0 and less than {}, got {}",
+                max_safe_radius, self.asteroids.safe_radius
+            ));
+        }
+        positive_size(
+            &mut problems,
+            "asteroids.large_size",
+            self.asteroids.large_size,
+        );
+        positive_size(
+            &mut problems,
+            "asteroids.medium_size",
+            self.asteroids.medium_size,
+        );
+        positive_size(
+            &mut problems,
+            "asteroids.small_size",
+            self.asteroids.small_size,
+        );
+
+        let saucers = &self.saucers;
+        positive(
+            &mut problems,
+            "saucers.spawn_interval",
+            saucers.spawn_interval,
+        );
+        positive(
+            &mut problems,
+            "saucers.spawn_interval_scale",
+            saucers.spawn_interval_scale,
+        );
+        positive(
+            &mut problems,
+            "saucers.min_spawn_interval",
+            saucers.min_spawn_interval,
+        );
+        if !(0. ..=1.).contains(&saucers.small_chance) {
+            problems.push(format!(
+                "saucers.small_chance must be between 0 and 1, got {}",
+                saucers.small_chance
+            ));
+        }
+        non_negative(
+            &mut problems,
+            "saucers.small_chance_per_level",
+            saucers.small_chance_per_level,
+        );
+        positive(
+            &mut problems,
+            "saucers.large_screen_cross_time",
+            saucers.large_screen_cross_time,
+        );
+        positive(
+            &mut problems,
+            "saucers.small_screen_cross_time",
+            saucers.small_screen_cross_time,
+        );
+        positive(
+            &mut problems,
+            "saucers.direction_change_interval",
+            saucers.direction_change_interval,
+        );
+        positive(
+            &mut problems,
+            "saucers.large_fire_interval",
+            saucers.large_fire_interval,
+        );
+        positive(
+            &mut problems,
+            "saucers.small_fire_interval",
+            saucers.small_fire_interval,
+        );
+        non_negative(
+            &mut problems,
+            "saucers.small_aim_error",
+            saucers.small_aim_error,
+        );
+        positive(
+            &mut problems,
+            "saucers.projectile_screen_cross_time",
+            saucers.projectile_screen_cross_time,
+        );
+        positive(
+            &mut problems,
+            "saucers.projectile_range",
+            saucers.projectile_range,
+        );
+        positive_size(&mut problems, "saucers.large_size", saucers.large_size);
+        positive_size(&mut problems, "saucers.small_size", saucers.small_size);
+
+        if self.boundaries.levels.contains_key(&0) {
+            problems.push("boundaries.levels start at level 1".to_string());
+        }
+
+        let world = &self.world;
+        // a smaller arena than the screen would leave the view looking past its edges
+        if !(world.screens.is_finite() && world.screens.cmpge(Vec2::ONE).all()) {
+            problems.push(format!(
+                "world.screens must be at least 1 in both directions, got ({}, {})",
+                world.screens.x, world.screens.y
+            ));
+        }
+        non_negative(&mut problems, "world.camera_lag", world.camera_lag);
+        non_negative(
+            &mut problems,
+            "world.camera_look_ahead",
+            world.camera_look_ahead,
+        );
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(TuningError::Invalid(problems))
+        }
+    }
+}
+
+fn positive(problems: &mut Vec<String>, name: &str, value: f32) {
+    if !(value.is_finite() && value > 0.) {
+        problems.push(format!("{} must be greater than 0, got {}", name, value));
+    }
+}
+
+fn non_negative(problems: &mut Vec<String>, name: &str, value: f32) {
+    if !(value.is_finite() && value >= 0.) {
+        problems.push(format!("{} must be 0 or more, got {}", name, value));
+    }
+}
+
+fn positive_size(problems: &mut Vec<String>, name: &str, value: Vec2) {
+    if !(value.is_finite() && value.cmpgt(Vec2::ZERO).all()) {
+        problems.push(format!(
+            "{} must be greater than 0 in both directions, got ({}, {})",
+            name, value.x, value.y
+        ));
+    }
+}
+
+#[derive(Debug)]
+pub enum TuningError {
+    Io(std::io::Error),
+    Parse(ron::error::SpannedError),
+    Invalid(Vec<String>),
+}
+
+impl fmt::Display for TuningError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TuningError::Io(err) => write!(f, "{}", err),
+            TuningError::Parse(err) => write!(f, "{}", err),
+            TuningError::Invalid(problems) => write!(f, "{}", problems.join(", ")),
+        }
+    }
+}
+
+impl std::error::Error for TuningError {}
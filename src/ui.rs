@@ -1,11 +1,106 @@
 use bevy::prelude::*;
 
-use crate::{GameAssets, Level, Lives, Score, LIFE_BONUS};
+use crate::constants::*;
+use crate::{
+    Arena, CameraScaling, GameAssets, GameSeed, GameState, HighScores, InGameSet, Level, Lives,
+    PauseMenu, PauseMenuItem, PausePage, PendingInitials, PlayerRespawn, Playing, Score, Settings,
+    Tuning, TuningNotice, INITIALS_LENGTH,
+};
+
+pub struct UiPlugin;
+
+impl Plugin for UiPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup_camera)
+            .add_systems(
+                OnEnter(GameState::NewGame),
+                setup_title_screen.after(crate::reset_score),
+            )
+            .add_systems(OnExit(GameState::NewGame), despawn_title_screen)
+            .add_systems(
+                OnEnter(GameState::LevelComplete),
+                setup_level_complete_screen,
+            )
+            .add_systems(
+                OnExit(GameState::LevelComplete),
+                despawn_level_complete_screen,
+            )
+            .add_systems(OnEnter(Playing), setup_ingame_ui)
+            .add_systems(
+                Update,
+                (update_score_ui, update_lives_ui, update_respawn_ui).in_set(InGameSet::Ui),
+            )
+            .add_systems(OnExit(Playing), despawn_ingame_ui)
+            .add_systems(OnEnter(GameState::GameOver), setup_gameover_screen)
+            .add_systems(OnExit(GameState::GameOver), despawn_gameover_screen)
+            .add_systems(OnEnter(GameState::EnterInitials), setup_initials_screen)
+            .add_systems(
+                Update,
+                update_initials_ui.run_if(in_state(GameState::EnterInitials)),
+            )
+            .add_systems(OnExit(GameState::EnterInitials), despawn_initials_screen)
+            .add_systems(
+                Update,
+                setup_pause_screen.run_if(
+                    in_state(GameState::Paused).and_then(
+                        resource_changed::<PauseMenu>.or_else(resource_changed::<Settings>),
+                    ),
+                ),
+            )
+            .add_systems(OnExit(GameState::Paused), despawn_pause_screen)
+            .add_systems(
+                Update,
+                crate::draw_line_gizmo.run_if(
+                    in_state(Playing)
+                        .and_then(|settings: Res<Settings>| settings.show_collision_hulls),
+                ),
+            )
+            .add_systems(Update, fit_background.run_if(resource_changed::<Arena>))
+            .add_systems(OnEnter(GameState::Processing), setup_tuning_notice)
+            .add_systems(
+                Update,
+                update_tuning_notice.run_if(resource_changed::<TuningNotice>),
+            );
+    }
+}
+
+/// Covers the arena, sized to it by `fit_background`.
+#[derive(Component)]
+pub struct Background;
+
+pub fn setup_camera(mut commands: Commands, arena: Res<Arena>) {
+    commands.spawn(Camera2dBundle::default());
+
+    commands.spawn((
+        Background,
+        SpriteBundle {
+            sprite: Sprite {
+                color: BACKGROUND_COLOR,
+                ..default()
+            },
+            transform: Transform {
+                scale: arena.size.extend(0.0),
+                ..default()
+            },
+            ..default()
+        },
+    ));
+}
+
+pub fn fit_background(arena: Res<Arena>, mut query: Query<&mut Transform, With<Background>>) {
+    for mut transform in query.iter_mut() {
+        transform.scale = arena.size.extend(0.0);
+    }
+}
 
 #[derive(Component)]
 pub struct TitleScreen;
 
-pub fn setup_title_screen(mut commands: Commands, assets: Res<GameAssets>) {
+pub fn setup_title_screen(
+    mut commands: Commands,
+    assets: Res<GameAssets>,
+    high_scores: Option<Res<HighScores>>,
+) {
     let font = &assets.font;
     commands
         .spawn(NodeBundle {
@@ -14,13 +109,34 @@ pub fn setup_title_screen(mut commands: Commands, assets: Res<GameAssets>) {
         })
         .insert(TitleScreen)
         .with_children(|parent| {
-            parent.spawn(TextBundle::from_section("Stroids", h1_style(&font)));
+            parent.spawn(TextBundle::from_section("Stroids", h1_style(font)));
         })
         .with_children(|parent| {
             parent.spawn(TextBundle::from_section(
                 "Press [Enter] to Play",
-                h3_style(&font),
+                h3_style(font),
             ));
+        })
+        .with_children(|parent| {
+            let Some(high_scores) = high_scores else {
+                return;
+            };
+            if high_scores.entries.is_empty() {
+                return;
+            }
+
+            parent.spawn(TextBundle::from_section("High Scores", h2_style(font)));
+            for (rank, entry) in high_scores.entries.iter().enumerate() {
+                let entry_text = format!(
+                    "{:>2}. {:<3} {:>8}  Level {:<3} {}",
+                    rank + 1,
+                    entry.initials,
+                    entry.score,
+                    entry.level,
+                    entry.date
+                );
+                parent.spawn(TextBundle::from_section(entry_text, h3_style(font)));
+            }
         });
 }
 
@@ -31,63 +147,93 @@ pub fn despawn_title_screen(mut commands: Commands, query: Query<Entity, With<Ti
 }
 
 #[derive(Component)]
-pub struct DiedScreen;
+pub struct GameOverScreen;
 
-pub fn setup_died_screen(
+pub fn setup_gameover_screen(
     mut commands: Commands,
-    lives_query: Query<&Lives>,
     assets: Res<GameAssets>,
+    score: Res<Score>,
+    seed: Res<GameSeed>,
 ) {
-    let Lives(lives) = lives_query.single();
     let font = &assets.font;
-    let lives_remaining = format!("{} lives remaining", lives);
+    let score_text = format!("Final Score: {}", score.0);
+    let seed_text = format!("Seed: {}", seed.0);
     commands
         .spawn(NodeBundle {
             style: ui_screen_style(),
             ..default()
         })
-        .insert(DiedScreen)
+        .insert(GameOverScreen)
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section("Game Over", h1_style(font)));
+        })
         .with_children(|parent| {
-            parent.spawn(TextBundle::from_section("You Died", h1_style(&font)));
+            parent.spawn(TextBundle::from_section(score_text, h2_style(font)));
         })
         .with_children(|parent| {
-            parent.spawn(TextBundle::from_section(lives_remaining, h2_style(&font)));
+            parent.spawn(TextBundle::from_section(seed_text, h3_style(font)));
         })
         .with_children(|parent| {
-            parent.spawn(TextBundle::from_section("Press [Enter]", h3_style(&font)));
+            parent.spawn(TextBundle::from_section("Press [Enter]", h3_style(font)));
         });
 }
 
-pub fn despawn_died_screen(mut commands: Commands, query: Query<Entity, With<DiedScreen>>) {
+pub fn despawn_gameover_screen(mut commands: Commands, query: Query<Entity, With<GameOverScreen>>) {
     for entity in query.iter() {
         commands.entity(entity).despawn_recursive();
     }
 }
 
 #[derive(Component)]
-pub struct GameOverScreen;
+pub struct InitialsScreen;
+
+#[derive(Component)]
+pub struct InitialsText;
 
-pub fn setup_gameover_screen(mut commands: Commands, assets: Res<GameAssets>, score: Res<Score>) {
+pub fn setup_initials_screen(mut commands: Commands, assets: Res<GameAssets>, score: Res<Score>) {
     let font = &assets.font;
-    let score_text = format!("Final Score: {}", score.0);
+    let score_text = format!("Score: {}", score.0);
     commands
         .spawn(NodeBundle {
             style: ui_screen_style(),
             ..default()
         })
-        .insert(GameOverScreen)
+        .insert(InitialsScreen)
         .with_children(|parent| {
-            parent.spawn(TextBundle::from_section("Game Over", h1_style(&font)));
+            parent.spawn(TextBundle::from_section("New High Score", h1_style(font)));
         })
         .with_children(|parent| {
-            parent.spawn(TextBundle::from_section(score_text, h2_style(&font)));
+            parent.spawn(TextBundle::from_section(score_text, h2_style(font)));
         })
         .with_children(|parent| {
-            parent.spawn(TextBundle::from_section("Press [Enter]", h3_style(&font)));
+            parent
+                .spawn(TextBundle::from_section(initials_text(""), h1_style(font)))
+                .insert(InitialsText);
+        })
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "Type your initials, [Enter] to save",
+                h3_style(font),
+            ));
         });
 }
 
-pub fn despawn_gameover_screen(mut commands: Commands, query: Query<Entity, With<GameOverScreen>>) {
+pub fn update_initials_ui(
+    initials: Res<PendingInitials>,
+    mut initials_text_query: Query<&mut Text, With<InitialsText>>,
+) {
+    if initials.is_changed() {
+        if let Ok(mut text) = initials_text_query.get_single_mut() {
+            text.sections[0].value = initials_text(&initials.0);
+        }
+    }
+}
+
+fn initials_text(initials: &str) -> String {
+    format!("{:_<width$}", initials, width = INITIALS_LENGTH)
+}
+
+pub fn despawn_initials_screen(mut commands: Commands, query: Query<Entity, With<InitialsScreen>>) {
     for entity in query.iter() {
         commands.entity(entity).despawn_recursive();
     }
@@ -102,6 +248,7 @@ pub fn setup_level_complete_screen(
     score: Res<Score>,
     level_query: Query<&Level>,
     lives_query: Query<&Lives>,
+    tuning: Res<Tuning>,
 ) {
     let next_level = level_query.single();
     let lives = lives_query.single();
@@ -110,7 +257,7 @@ pub fn setup_level_complete_screen(
     let score_text = format!(
         "Score: {} (Lives Bonus: {})",
         score.0,
-        lives.0 as u64 * (LIFE_BONUS * (next_level.0 - 1) as u64)
+        lives.0 as u64 * (tuning.scoring.life_bonus * (next_level.0 - 1) as u64)
     );
     commands
         .spawn(NodeBundle {
@@ -119,16 +266,16 @@ pub fn setup_level_complete_screen(
         })
         .insert(LevelCompleteScreen)
         .with_children(|parent| {
-            parent.spawn(TextBundle::from_section("Level Complete", h1_style(&font)));
+            parent.spawn(TextBundle::from_section("Level Complete", h1_style(font)));
         })
         .with_children(|parent| {
-            parent.spawn(TextBundle::from_section(score_text, h2_style(&font)));
+            parent.spawn(TextBundle::from_section(score_text, h2_style(font)));
         })
         .with_children(|parent| {
-            parent.spawn(TextBundle::from_section(next_level_text, h2_style(&font)));
+            parent.spawn(TextBundle::from_section(next_level_text, h2_style(font)));
         })
         .with_children(|parent| {
-            parent.spawn(TextBundle::from_section("Press [Enter]", h3_style(&font)));
+            parent.spawn(TextBundle::from_section("Press [Enter]", h3_style(font)));
         });
 }
 
@@ -147,6 +294,12 @@ pub struct InGameUi;
 #[derive(Component)]
 pub struct ScoreText;
 
+#[derive(Component)]
+pub struct LivesText;
+
+#[derive(Component)]
+pub struct RespawnText;
+
 pub fn setup_ingame_ui(
     mut commands: Commands,
     assets: Res<GameAssets>,
@@ -168,14 +321,16 @@ pub fn setup_ingame_ui(
         })
         .insert(InGameUi)
         .with_children(|parent| {
-            parent.spawn(TextBundle::from_section(lives_text, h3_style(&font)));
+            parent
+                .spawn(TextBundle::from_section(lives_text, h3_style(font)))
+                .insert(LivesText);
         })
         .with_children(|parent| {
-            parent.spawn(TextBundle::from_section("|", h3_style(&font)));
+            parent.spawn(TextBundle::from_section("|", h3_style(font)));
         })
         .with_children(|parent| {
             parent
-                .spawn(TextBundle::from_section(score_text, h3_style(&font)))
+                .spawn(TextBundle::from_section(score_text, h3_style(font)))
                 .insert(ScoreText);
         });
 
@@ -186,7 +341,22 @@ pub fn setup_ingame_ui(
         })
         .insert(InGameUi)
         .with_children(|parent| {
-            parent.spawn(TextBundle::from_section(level_text, h3_style(&font)));
+            parent.spawn(TextBundle::from_section(level_text, h3_style(font)));
+        });
+
+    commands
+        .spawn(NodeBundle {
+            style: ui_screen_style(),
+            ..default()
+        })
+        .insert(InGameUi)
+        .with_children(|parent| {
+            parent
+                .spawn(TextBundle {
+                    visibility: Visibility::Hidden,
+                    ..TextBundle::from_section("Waiting for clear space", h2_style(font))
+                })
+                .insert(RespawnText);
         });
 }
 
@@ -204,6 +374,31 @@ pub fn update_score_ui(score: Res<Score>, mut score_text_query: Query<&mut Text,
     }
 }
 
+pub fn update_lives_ui(
+    lives_query: Query<&Lives, Changed<Lives>>,
+    mut lives_text_query: Query<&mut Text, With<LivesText>>,
+) {
+    if let Ok(lives) = lives_query.get_single() {
+        if let Ok(mut text) = lives_text_query.get_single_mut() {
+            text.sections[0].value = format!("Lives: {}", lives.0);
+        }
+    }
+}
+
+pub fn update_respawn_ui(
+    respawn: Option<Res<PlayerRespawn>>,
+    mut respawn_text_query: Query<&mut Visibility, With<RespawnText>>,
+) {
+    let waiting = respawn.is_some_and(|respawn| respawn.waiting_for_clear_space());
+    if let Ok(mut visibility) = respawn_text_query.get_single_mut() {
+        visibility.set_if_neq(if waiting {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        });
+    }
+}
+
 fn ui_screen_style() -> Style {
     Style {
         width: Val::Percent(100.0),
@@ -217,6 +412,153 @@ fn ui_screen_style() -> Style {
     }
 }
 
+#[derive(Component)]
+pub struct PauseScreen;
+
+/// Built again whenever the selection or a setting changes.
+pub fn setup_pause_screen(
+    mut commands: Commands,
+    assets: Res<GameAssets>,
+    menu: Res<PauseMenu>,
+    settings: Res<Settings>,
+    query: Query<Entity, With<PauseScreen>>,
+) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let font = &assets.font;
+    let title = match menu.page {
+        PausePage::Main => "Paused",
+        PausePage::Settings => "Settings",
+    };
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                ..ui_screen_style()
+            },
+            background_color: Color::srgba(0., 0., 0., 0.6).into(),
+            ..default()
+        })
+        .insert(PauseScreen)
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(title, h1_style(font)));
+        })
+        .with_children(|parent| {
+            for (index, item) in menu.page.items().iter().enumerate() {
+                let label = pause_menu_label(*item, &settings);
+                let (text, color) = if index == menu.selected {
+                    (format!("> {} <", label), Color::WHITE)
+                } else {
+                    (label, Color::srgb(0.6, 0.6, 0.6))
+                };
+                parent.spawn(TextBundle::from_section(
+                    text,
+                    TextStyle {
+                        color,
+                        ..h2_style(font)
+                    },
+                ));
+            }
+        })
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "[Up]/[Down] to choose, [Enter] to select, [Esc] to go back",
+                h3_style(font),
+            ));
+        });
+}
+
+fn pause_menu_label(item: PauseMenuItem, settings: &Settings) -> String {
+    match item {
+        PauseMenuItem::Resume => "Resume".to_string(),
+        PauseMenuItem::RestartLevel => "Restart Level".to_string(),
+        PauseMenuItem::Settings => "Settings".to_string(),
+        PauseMenuItem::QuitToTitle => "Quit to Title".to_string(),
+        PauseMenuItem::ShowCollisionHulls => format!(
+            "Collision Hulls: {}",
+            if settings.show_collision_hulls {
+                "On"
+            } else {
+                "Off"
+            }
+        ),
+        PauseMenuItem::Fullscreen => {
+            format!(
+                "Fullscreen: {}",
+                if settings.fullscreen { "On" } else { "Off" }
+            )
+        }
+        PauseMenuItem::Resolution => {
+            let (width, height) = RESOLUTIONS[settings.resolution];
+            format!("Resolution: {}x{}", width, height)
+        }
+        PauseMenuItem::CameraScaling => format!(
+            "Scaling: {}",
+            match settings.camera_scaling {
+                CameraScaling::Letterbox => "Letterbox",
+                CameraScaling::Expand => "Expand",
+            }
+        ),
+        PauseMenuItem::Back => "Back".to_string(),
+    }
+}
+
+pub fn despawn_pause_screen(mut commands: Commands, query: Query<Entity, With<PauseScreen>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Stays up over every screen, empty unless the tuning file has a problem.
+#[derive(Component)]
+pub struct TuningNoticeText;
+
+pub fn setup_tuning_notice(
+    mut commands: Commands,
+    assets: Res<GameAssets>,
+    notice: Res<TuningNotice>,
+) {
+    let text = notice.0.clone().unwrap_or_default();
+    commands
+        .spawn(NodeBundle {
+            style: notice_ui_style(),
+            z_index: ZIndex::Global(1),
+            ..default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn(TextBundle::from_section(
+                    text,
+                    TextStyle {
+                        color: Color::srgb(1., 0.3, 0.3),
+                        ..h3_style(&assets.font)
+                    },
+                ))
+                .insert(TuningNoticeText);
+        });
+}
+
+pub fn update_tuning_notice(
+    notice: Res<TuningNotice>,
+    mut notice_text_query: Query<&mut Text, With<TuningNoticeText>>,
+) {
+    if let Ok(mut text) = notice_text_query.get_single_mut() {
+        text.sections[0].value = notice.0.clone().unwrap_or_default();
+    }
+}
+
+fn notice_ui_style() -> Style {
+    Style {
+        position_type: PositionType::Absolute,
+        width: Val::Percent(100.0),
+        bottom: Val::Px(10.),
+        padding: UiRect::horizontal(Val::Px(10.)),
+        ..default()
+    }
+}
+
 fn ingame_ui_style() -> Style {
     Style {
         width: Val::Percent(100.0),
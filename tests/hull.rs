@@ -0,0 +1,555 @@
+use bevy::prelude::*;
+use bevy::render::{
+    render_asset::RenderAssetUsages,
+    render_resource::{Extent3d, TextureDimension, TextureFormat},
+};
+use stroids::{
+    check_for_collision, check_world_hull_collision, Hull, HullError, HullOptions, WorldHull,
+};
+
+/// A box `size` big centred on `position`.
+fn rectangle(position: Vec2, size: Vec2) -> WorldHull {
+    WorldHull::new(
+        &Hull::unit_square(),
+        &Transform::from_translation(position.extend(0.)).with_scale(size.extend(1.)),
+    )
+}
+
+fn assert_near(actual: Vec2, expected: Vec2, what: &str) {
+    assert!(
+        actual.distance(expected) < 1e-4,
+        "{} is {} not {}",
+        what,
+        actual,
+        expected
+    );
+}
+
+#[test]
+fn boxes_overlapping_sideways_push_apart_sideways() {
+    // 10 boxes 8 apart across and 3 up overlap by 2 across and 7 up, so across is shallowest
+    let a = rectangle(Vec2::ZERO, Vec2::splat(10.));
+    let b = rectangle(Vec2::new(8., 3.), Vec2::splat(10.));
+
+    let contact = check_world_hull_collision(&a, &b).expect("the boxes overlap");
+    assert_near(contact.normal, Vec2::X, "normal");
+    assert!(
+        (contact.depth - 2.).abs() < 1e-4,
+        "depth is {}",
+        contact.depth
+    );
+    // halfway between the middle of a's right side and the middle of b's left side inside it
+    assert_near(contact.point, Vec2::new(4., 1.5), "point");
+
+    // the other way round the normal still points from the first box to the second
+    let contact = check_world_hull_collision(&b, &a).expect("the boxes overlap swapped");
+    assert_near(contact.normal, Vec2::NEG_X, "swapped normal");
+    assert!(
+        (contact.depth - 2.).abs() < 1e-4,
+        "swapped depth is {}",
+        contact.depth
+    );
+    assert_near(contact.point, Vec2::new(4., 1.5), "swapped point");
+}
+
+#[test]
+fn boxes_overlapping_vertically_push_apart_vertically() {
+    // a wide box with a small one 1 into its bottom edge, 7 into it across
+    let a = rectangle(Vec2::ZERO, Vec2::new(20., 10.));
+    let b = rectangle(Vec2::new(-3., -8.), Vec2::new(4., 8.));
+
+    let contact = check_world_hull_collision(&a, &b).expect("the boxes overlap");
+    assert_near(contact.normal, Vec2::NEG_Y, "normal");
+    assert!(
+        (contact.depth - 1.).abs() < 1e-4,
+        "depth is {}",
+        contact.depth
+    );
+    // a's whole bottom edge is deepest, so the middle of it, and the middle of b's top edge
+    assert_near(contact.point, Vec2::new(-1.5, -4.5), "point");
+
+    // moving the second box by the normal times the depth leaves them just touching
+    let separated = rectangle(
+        Vec2::new(-3., -8.) + contact.normal * contact.depth,
+        Vec2::new(4., 8.),
+    );
+    let touching = check_world_hull_collision(&a, &separated).map_or(0., |contact| contact.depth);
+    assert!(touching < 1e-4, "still {} deep after separating", touching);
+}
+
+#[test]
+fn transforming_per_test_gives_the_same_contact() {
+    let hull = Hull::unit_square();
+    let transform_a = Transform::from_scale(Vec3::new(10., 10., 1.));
+    let transform_b = Transform::from_xyz(8., 3., 0.).with_scale(Vec3::new(10., 10., 1.));
+
+    assert_eq!(
+        check_for_collision(&hull, &transform_a, &hull, &transform_b),
+        check_world_hull_collision(
+            &WorldHull::new(&hull, &transform_a),
+            &WorldHull::new(&hull, &transform_b)
+        )
+    );
+}
+
+/// An image `width` by `height` in `format`, with `pixel` giving the bytes of each pixel from its
+/// alpha, row by row from the top left.
+fn image_from_alphas(
+    format: TextureFormat,
+    width: u32,
+    height: u32,
+    alphas: &[f32],
+    pixel: impl Fn(f32) -> Vec<u8>,
+) -> Image {
+    Image::new(
+        Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        alphas.iter().flat_map(|alpha| pixel(*alpha)).collect(),
+        format,
+        RenderAssetUsages::default(),
+    )
+}
+
+/// The bytes of a pixel with the given alpha.
+type PixelBytes = fn(f32) -> Vec<u8>;
+
+fn unorm8(alpha: f32) -> u8 {
+    (alpha * 255.) as u8
+}
+
+fn unorm16(alpha: f32) -> [u8; 2] {
+    ((alpha * 65535.) as u16).to_le_bytes()
+}
+
+/// Half float bits of a value the tests use, 0 or 1.
+fn half(value: f32) -> [u8; 2] {
+    let bits: u16 = if value > 0. { 0x3c00 } else { 0 };
+    bits.to_le_bytes()
+}
+
+/// Every format hulls can be traced from, with the bytes of a pixel of a given alpha. The other
+/// channels are all ones, so reading the wrong one shows up as a pixel that's wrongly visible or
+/// hidden.
+fn supported_formats() -> Vec<(TextureFormat, PixelBytes)> {
+    vec![
+        (TextureFormat::Rgba8Unorm, |a| {
+            vec![0xff, 0xff, 0xff, unorm8(a)]
+        }),
+        (TextureFormat::Rgba8UnormSrgb, |a| {
+            vec![0xff, 0xff, 0xff, unorm8(a)]
+        }),
+        (TextureFormat::Bgra8Unorm, |a| {
+            vec![0xff, 0xff, 0xff, unorm8(a)]
+        }),
+        (TextureFormat::Bgra8UnormSrgb, |a| {
+            vec![0xff, 0xff, 0xff, unorm8(a)]
+        }),
+        (TextureFormat::R8Unorm, |a| vec![unorm8(a)]),
+        (TextureFormat::Rg8Unorm, |a| vec![0xff, unorm8(a)]),
+        (TextureFormat::R16Unorm, |a| unorm16(a).to_vec()),
+        (TextureFormat::Rg16Unorm, |a| {
+            [[0xff; 2], unorm16(a)].concat()
+        }),
+        (TextureFormat::Rgba16Unorm, |a| {
+            [[0xff; 6].as_slice(), &unorm16(a)].concat()
+        }),
+        (TextureFormat::R16Float, |a| half(a).to_vec()),
+        (TextureFormat::Rg16Float, |a| [[0xff; 2], half(a)].concat()),
+        (TextureFormat::Rgba16Float, |a| {
+            [[0xff; 6].as_slice(), &half(a)].concat()
+        }),
+        (TextureFormat::R32Float, |a| a.to_le_bytes().to_vec()),
+        (TextureFormat::Rg32Float, |a| {
+            [[0xff; 4], a.to_le_bytes()].concat()
+        }),
+        (TextureFormat::Rgba32Float, |a| {
+            [[0xff; 12].as_slice(), &a.to_le_bytes()].concat()
+        }),
+    ]
+}
+
+/// Traced exactly, so the hull is the visible pixels themselves, at one unit a pixel.
+fn exact_hull(image: &Image) -> Result<Hull, HullError> {
+    let options = HullOptions {
+        concave: true,
+        ..HullOptions::default()
+    };
+    Hull::new(image, &options).map(|hull| hull.sized(image.size_f32()))
+}
+
+fn area(hull: &Hull) -> f32 {
+    hull.pieces()
+        .iter()
+        .map(|piece| {
+            piece
+                .windows(2)
+                .map(|edge| edge[0].perp_dot(edge[1]))
+                .sum::<f32>()
+                / 2.
+        })
+        .sum()
+}
+
+fn vertex_bounds(hull: &Hull) -> Rect {
+    let mut vertices = hull.pieces().iter().flatten();
+    let first = *vertices.next().expect("a hull has vertices");
+    vertices.fold(Rect::from_corners(first, first), |bounds, v| {
+        bounds.union_point(*v)
+    })
+}
+
+#[test]
+fn every_supported_format_masks_by_its_alpha() {
+    // an L of three pixels in a 3 by 2 image, only the top left two and the one below the second
+    let alphas = [1., 1., 0., 0., 1., 0.];
+
+    for (format, pixel) in supported_formats() {
+        let image = image_from_alphas(format, 3, 2, &alphas, pixel);
+        let hull = exact_hull(&image).unwrap_or_else(|err| panic!("{:?}: {}", format, err));
+
+        assert!(
+            (area(&hull) - 3.).abs() < 1e-4,
+            "{:?}: covers {} pixels not 3",
+            format,
+            area(&hull)
+        );
+        let bounds = vertex_bounds(&hull);
+        assert_eq!(
+            (bounds.min, bounds.max),
+            (Vec2::new(-1.5, -1.), Vec2::new(0.5, 1.)),
+            "{:?}: the pixels are in the wrong place",
+            format
+        );
+
+        let transparent = image_from_alphas(format, 3, 2, &[0.; 6], pixel);
+        assert!(
+            matches!(exact_hull(&transparent), Err(HullError::Transparent)),
+            "{:?}: a transparent image has a hull",
+            format
+        );
+    }
+}
+
+/// Whether a single pixel of half float `bits` is visible over `threshold`.
+fn half_visible(bits: u16, threshold: f32) -> bool {
+    let image = image_from_alphas(TextureFormat::R16Float, 1, 1, &[0.], |_| {
+        bits.to_le_bytes().to_vec()
+    });
+    let options = HullOptions {
+        alpha_threshold: threshold,
+        ..HullOptions::default()
+    };
+    match Hull::new(&image, &options) {
+        Ok(_) => true,
+        Err(HullError::Transparent) => false,
+        Err(err) => panic!("{:04x}: {}", bits, err),
+    }
+}
+
+#[test]
+fn half_floats_decode_at_the_edges() {
+    // the smallest subnormal is still more than nothing
+    assert!(half_visible(0x0001, 0.));
+    assert!(!half_visible(0x0000, 0.));
+    assert!(!half_visible(0x8000, 0.), "negative zero");
+    assert!(!half_visible(0x8001, 0.), "negative subnormal");
+    // half way up the subnormals is 2^-15
+    assert!(half_visible(0x0200, 2f32.powi(-15) * 0.99));
+    assert!(!half_visible(0x0200, 2f32.powi(-15)));
+    // the largest subnormal is just under the smallest normal, 2^-14
+    assert!(!half_visible(0x03ff, 2f32.powi(-14)));
+    assert!(half_visible(0x0400, 2f32.powi(-14) * 0.99));
+
+    // 1.0 and 0.5 are exact, and only more than the threshold counts
+    assert!(half_visible(0x3c00, 0.99));
+    assert!(!half_visible(0x3c00, 1.));
+    assert!(!half_visible(0x3800, 0.5));
+    assert!(half_visible(0x3801, 0.5));
+    assert!(!half_visible(0x37ff, 0.5));
+
+    assert!(half_visible(0x7c00, 1.), "infinity");
+    assert!(!half_visible(0x7e00, 0.), "NaN");
+}
+
+#[test]
+fn unsupported_formats_and_short_data_are_errors() {
+    let image = image_from_alphas(TextureFormat::Rgba8Sint, 2, 2, &[1.; 4], |_| vec![0x7f; 4]);
+    assert!(matches!(
+        Hull::new(&image, &HullOptions::default()),
+        Err(HullError::UnsupportedFormat(TextureFormat::Rgba8Sint))
+    ));
+
+    let mut image = image_from_alphas(TextureFormat::Rgba8Unorm, 2, 2, &[1.; 4], |_| vec![0xff; 4]);
+    image.data.truncate(10);
+    assert!(matches!(
+        Hull::new(&image, &HullOptions::default()),
+        Err(HullError::MissingPixelData {
+            expected: 16,
+            found: 10
+        })
+    ));
+}
+
+/// A `size` square RGBA image with a blob in the middle whose edge wanders in and out by a few
+/// pixels, so its convex hull has many corners to trim.
+fn noisy_blob(size: u32) -> (Image, Vec<(u32, u32)>) {
+    let centre = size as f32 / 2.;
+    // a fixed linear congruential sequence, the same blob every run
+    let mut state = 0x2545_f491_u32;
+    let wobble: Vec<f32> = (0..64)
+        .map(|_| {
+            state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            (state >> 24) as f32 / 255. * 12.
+        })
+        .collect();
+
+    let mut visible = Vec::new();
+    let alphas: Vec<f32> = (0..size * size)
+        .map(|i| {
+            let (x, y) = (i % size, i / size);
+            let offset = Vec2::new(x as f32 + 0.5 - centre, y as f32 + 0.5 - centre);
+            let turn = (offset.to_angle() / std::f32::consts::TAU + 0.5) * wobble.len() as f32;
+            let radius = size as f32 * 0.35 - wobble[turn as usize % wobble.len()];
+            if offset.length() < radius {
+                visible.push((x, y));
+                1.
+            } else {
+                0.
+            }
+        })
+        .collect();
+
+    let image = image_from_alphas(TextureFormat::Rgba8Unorm, size, size, &alphas, |a| {
+        vec![0xff, 0xff, 0xff, unorm8(a)]
+    });
+    (image, visible)
+}
+
+/// Where `offset` into pixel `x`, `y` lands in the hull of an image `size` big, sized to it.
+fn pixel_point(size: UVec2, x: u32, y: u32, offset: Vec2) -> Vec2 {
+    let half = size.as_vec2() / 2.;
+    Vec2::new(x as f32 + offset.x - half.x, half.y - (y as f32 + offset.y))
+}
+
+/// Whether `point` is inside the convex `piece` or on its edge.
+fn piece_holds(piece: &[Vec2], point: Vec2) -> bool {
+    piece
+        .windows(2)
+        .all(|edge| (edge[1] - edge[0]).perp_dot(point - edge[0]) >= -1e-3)
+}
+
+/// Whether all four corners of pixel `x`, `y` of an image `size` big are inside the hull.
+fn holds_pixel(hull: &Hull, size: UVec2, x: u32, y: u32) -> bool {
+    [(0., 0.), (1., 0.), (0., 1.), (1., 1.)]
+        .iter()
+        .all(|&(dx, dy)| {
+            let corner = pixel_point(size, x, y, Vec2::new(dx, dy));
+            hull.pieces().iter().any(|piece| piece_holds(piece, corner))
+        })
+}
+
+#[test]
+fn simplified_hulls_hold_every_visible_pixel_within_their_corner_budget() {
+    let size = 64;
+    let (image, visible) = noisy_blob(size);
+
+    let exact = Hull::new(&image, &HullOptions::default()).expect("the blob has a hull");
+    let exact_corners = exact.pieces()[0].len() - 1;
+    assert!(
+        exact_corners > 20,
+        "the blob only has {} corners, not enough to need trimming",
+        exact_corners
+    );
+
+    let budgets = (3..=12).map(Some).chain([None]);
+    for max_vertices in budgets {
+        for max_error in [0., 1., 4.] {
+            let options = HullOptions {
+                max_vertices,
+                max_error,
+                ..HullOptions::default()
+            };
+            let hull = Hull::new(&image, &options)
+                .expect("the blob has a hull")
+                .sized(image.size_f32());
+
+            let corners = hull.pieces()[0].len() - 1;
+            assert!(
+                corners <= max_vertices.unwrap_or(exact_corners),
+                "{:?}: {} corners",
+                options,
+                corners
+            );
+            for &(x, y) in &visible {
+                assert!(
+                    holds_pixel(&hull, UVec2::splat(size), x, y),
+                    "{:?}: pixel {}, {} sticks out",
+                    options,
+                    x,
+                    y
+                );
+            }
+        }
+    }
+}
+
+/// An image with an opaque pixel for every `#` in `rows`, the top row first.
+fn mask_image(rows: &[&str]) -> Image {
+    let alphas: Vec<f32> = rows
+        .iter()
+        .flat_map(|row| row.chars().map(|c| if c == '#' { 1. } else { 0. }))
+        .collect();
+    image_from_alphas(
+        TextureFormat::Rgba8Unorm,
+        rows[0].len() as u32,
+        rows.len() as u32,
+        &alphas,
+        |a| vec![0xff, 0xff, 0xff, unorm8(a)],
+    )
+}
+
+/// Each pixel of `rows`, whether it's opaque and where its centre is in the image's hull.
+fn pixel_centres<'a>(rows: &'a [&str]) -> impl Iterator<Item = (bool, Vec2)> + 'a {
+    let size = UVec2::new(rows[0].len() as u32, rows.len() as u32);
+    rows.iter().enumerate().flat_map(move |(y, row)| {
+        row.chars().enumerate().map(move |(x, c)| {
+            let centre = pixel_point(size, x as u32, y as u32, Vec2::splat(0.5));
+            (c == '#', centre)
+        })
+    })
+}
+
+/// An arrowhead pointing right, with a notch in the back like the ship's.
+const CHEVRON: [&str; 7] = [
+    "#.......", "###.....", ".#####..", "..######", ".#####..", "###.....", "#.......",
+];
+
+#[test]
+fn concave_outlines_split_into_convex_pieces_around_their_notches() {
+    let l = ["#...", "#...", "#...", "####"];
+    let c = ["#####", "#....", "#....", "#....", "#####"];
+
+    for rows in [&l[..], &c[..], &CHEVRON[..]] {
+        let hull = exact_hull(&mask_image(rows)).expect("the shape has a hull");
+        assert!(hull.pieces().len() > 1, "{:?} wasn't split", rows);
+
+        for piece in hull.pieces() {
+            assert_eq!(
+                piece.first(),
+                piece.last(),
+                "{:?}: {:?} isn't closed",
+                rows,
+                piece
+            );
+            let corners = &piece[..piece.len() - 1];
+            let count = corners.len();
+            // an outline corner can sit on a straight edge of the piece it ends up in
+            let convex = (0..count).all(|i| {
+                let before = corners[(i + count - 1) % count];
+                let after = corners[(i + 1) % count];
+                (corners[i] - before).perp_dot(after - corners[i]) >= 0.
+            });
+            let piece_area = area(&Hull::from_pieces(vec![piece.clone()]));
+            assert!(
+                convex && piece_area > 0.,
+                "{:?}: {:?} isn't convex",
+                rows,
+                piece
+            );
+        }
+
+        let opaque = rows
+            .iter()
+            .flat_map(|row| row.chars())
+            .filter(|c| *c == '#');
+        let expected = opaque.count() as f32;
+        assert!(
+            (area(&hull) - expected).abs() < 1e-3,
+            "{:?}: pieces cover {} pixels not {}",
+            rows,
+            area(&hull),
+            expected
+        );
+
+        for (opaque, centre) in pixel_centres(rows) {
+            let held = hull.pieces().iter().any(|piece| piece_holds(piece, centre));
+            assert_eq!(held, opaque, "{:?}: pixel at {}", rows, centre);
+        }
+    }
+}
+
+#[test]
+fn separate_shapes_take_the_convex_hull() {
+    let rows = ["##...", "##...", ".....", "...##", "...##"];
+    let image = mask_image(&rows);
+
+    let concave = exact_hull(&image).expect("the shapes have a hull");
+    let convex = Hull::new(&image, &HullOptions::default())
+        .expect("the shapes have a hull")
+        .sized(image.size_f32());
+
+    assert_eq!(concave.pieces(), convex.pieces());
+    for (_, centre) in pixel_centres(&rows).filter(|(opaque, _)| *opaque) {
+        assert!(piece_holds(&concave.pieces()[0], centre));
+    }
+    // the gap between them is filled in
+    assert!(piece_holds(&concave.pieces()[0], Vec2::ZERO));
+}
+
+#[test]
+fn concave_outlines_over_their_corner_budget_take_the_convex_hull() {
+    let image = mask_image(&CHEVRON);
+    let options = HullOptions {
+        max_vertices: Some(6),
+        concave: true,
+        ..HullOptions::default()
+    };
+
+    let hull = Hull::new(&image, &options).expect("the chevron has a hull");
+    let convex = Hull::new(
+        &image,
+        &HullOptions {
+            concave: false,
+            ..options.clone()
+        },
+    )
+    .expect("the chevron has a hull");
+
+    assert_eq!(hull.pieces(), convex.pieces());
+    assert!(hull.pieces()[0].len() - 1 <= 6);
+    // not the whole image, as when tracing fails
+    assert!(area(&hull) < 0.9, "covers {} of the image", area(&hull));
+}
+
+#[test]
+fn every_small_mask_traces_a_concave_hull_around_its_pixels() {
+    for (width, height) in [(3, 3), (4, 4)] {
+        let size = UVec2::new(width, height);
+        let pixels = width * height;
+
+        for bits in 1..1u32 << pixels {
+            let alphas: Vec<f32> = (0..pixels).map(|i| (bits >> i & 1) as f32).collect();
+            let image = image_from_alphas(TextureFormat::R8Unorm, width, height, &alphas, |a| {
+                vec![unorm8(a)]
+            });
+
+            let hull = exact_hull(&image)
+                .unwrap_or_else(|err| panic!("{}x{} mask {:b}: {}", width, height, bits, err));
+            let opaque = (0..pixels).filter(|i| bits >> i & 1 == 1);
+            for i in opaque {
+                assert!(
+                    holds_pixel(&hull, size, i % width, i / width),
+                    "{}x{} mask {:b}: pixel {} sticks out",
+                    width,
+                    height,
+                    bits,
+                    i
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,245 @@
+use bevy::prelude::*;
+use stroids::{
+    arena_boundary_system, check_world_hull_collision, check_wrapped_collision, collision_system,
+    update_spatial_hash, Arena, ArenaBoundary, Asteroid, AsteroidDestroyedEvent, AsteroidSize,
+    Hull, Invulnerable, Player, PlayerKilledEvent, Projectile, SpatialHash, Velocity, WorldHull,
+};
+
+/// A square `size` across centred on `position`.
+fn square(position: Vec2, size: f32) -> WorldHull {
+    WorldHull::new(
+        &Hull::unit_square(),
+        &Transform::from_translation(position.extend(0.)).with_scale(Vec3::splat(size)),
+    )
+}
+
+fn half_arena() -> Vec2 {
+    Arena::default().size / 2.
+}
+
+/// For each edge and corner: where a 64 square sits centred on it, half hanging over, and where a
+/// 16 square sits just inside the opposite side overlapping the part of the big one drawn there
+/// by 4. The direction is the way across the seam from the small square to the big one.
+fn across_seams() -> Vec<(&'static str, Vec2, Vec2, Vec2)> {
+    let half = half_arena();
+    let edge = |direction: Vec2| {
+        let hanging = direction * half;
+        // the big square's wrapped part reaches 32 in from the far side
+        let touching = -direction * (half - Vec2::splat(36.));
+        (hanging, touching, direction)
+    };
+
+    [
+        ("right", Vec2::X),
+        ("left", Vec2::NEG_X),
+        ("top", Vec2::Y),
+        ("bottom", Vec2::NEG_Y),
+        ("top right", Vec2::ONE),
+        ("bottom left", Vec2::NEG_ONE),
+        ("top left", Vec2::new(-1., 1.)),
+        ("bottom right", Vec2::new(1., -1.)),
+    ]
+    .into_iter()
+    .map(|(name, direction)| {
+        let (hanging, touching, direction) = edge(direction);
+        // edges only move along one axis, keep the other centred
+        let axis = direction.abs();
+        (name, hanging * axis, touching * axis, -direction)
+    })
+    .collect()
+}
+
+#[test]
+fn hulls_collide_across_every_edge_and_corner() {
+    let size = Arena::default().size;
+
+    for (name, hanging, touching, across) in across_seams() {
+        let big = square(hanging, 64.);
+        let small = square(touching, 16.);
+
+        assert!(
+            check_world_hull_collision(&small, &big).is_none(),
+            "{}: the squares are a whole arena apart without wrapping",
+            name
+        );
+
+        let contact = check_wrapped_collision(&small, &big, size)
+            .unwrap_or_else(|| panic!("{}: no collision across the seam", name));
+        assert!(
+            (contact.depth - 4.).abs() < 1e-3,
+            "{}: overlap is {} not 4",
+            name,
+            contact.depth
+        );
+        assert!(
+            contact.normal.dot(across) > 0.,
+            "{}: normal {} doesn't point across the seam towards the big square",
+            name,
+            contact.normal
+        );
+        // rather than the far side of the arena, a flat face can put it half a face along
+        assert!(
+            contact.point.distance(touching) < 32.,
+            "{}: contact {} isn't next to the small square at {}",
+            name,
+            contact.point,
+            touching
+        );
+
+        // swapped round the collision is on the big square's side of the seam
+        let contact = check_wrapped_collision(&big, &small, size)
+            .unwrap_or_else(|| panic!("{}: no collision across the seam swapped", name));
+        assert!(contact.normal.dot(across) < 0., "{}: swapped normal", name);
+        assert!(
+            contact.point.distance(hanging) < 64.,
+            "{}: swapped contact {} isn't next to the big square",
+            name,
+            contact.point
+        );
+    }
+}
+
+#[test]
+fn hulls_near_opposite_edges_but_not_touching_miss() {
+    let size = Arena::default().size;
+
+    for (name, hanging, touching, across) in across_seams() {
+        let big = square(hanging, 64.);
+        // moved away from the seam until there's a gap of 4
+        let small = square(touching - across * 8., 16.);
+
+        assert!(
+            check_wrapped_collision(&small, &big, size).is_none(),
+            "{}: collided with a gap across the seam",
+            name
+        );
+    }
+}
+
+#[test]
+fn hulls_inside_the_arena_collide_as_they_are() {
+    let size = Arena::default().size;
+    let a = square(Vec2::new(-20., 10.), 32.);
+    let b = square(Vec2::new(6., 10.), 32.);
+
+    assert_eq!(
+        check_wrapped_collision(&a, &b, size),
+        check_world_hull_collision(&a, &b)
+    );
+}
+
+#[test]
+fn spatial_hash_finds_neighbours_across_every_edge_and_corner() {
+    let size = Arena::default().size;
+    let entity = Entity::from_raw(7);
+    let mut found = Vec::new();
+
+    for (name, hanging, touching, _) in across_seams() {
+        let mut hash = SpatialHash::new(128.);
+        hash.insert(entity, hanging, 32.);
+        hash.query(touching, 8., &mut found);
+        assert!(
+            found.is_empty(),
+            "{}: found across the seam unwrapped",
+            name
+        );
+
+        let mut hash = SpatialHash::new(128.);
+        hash.set_wrap(Some(size));
+        hash.insert(entity, hanging, 32.);
+        hash.query(touching, 8., &mut found);
+        assert_eq!(found, vec![entity], "{}: querying the far side", name);
+
+        // the other way round, the querier hangs over the edge and the entity is just inside
+        let mut hash = SpatialHash::new(128.);
+        hash.set_wrap(Some(size));
+        hash.insert(entity, touching, 8.);
+        hash.query(hanging, 32., &mut found);
+        assert_eq!(found, vec![entity], "{}: querying over the edge", name);
+    }
+}
+
+fn collision_app() -> App {
+    let mut app = App::new();
+    app.add_event::<AsteroidDestroyedEvent>()
+        .add_event::<PlayerKilledEvent>()
+        .init_resource::<Arena>()
+        .insert_resource(SpatialHash::new(128.))
+        .add_systems(Update, (update_spatial_hash, collision_system).chain());
+    app
+}
+
+fn spawn_asteroid(app: &mut App, position: Vec2) {
+    app.world_mut().spawn((
+        Asteroid,
+        AsteroidSize::Large,
+        Transform::from_translation(position.extend(0.)),
+        Velocity(Vec2::ZERO),
+        square(position, 64.),
+    ));
+}
+
+#[test]
+fn ship_touching_a_wrapped_asteroid_dies_at_every_edge_and_corner() {
+    for (name, hanging, touching, _) in across_seams() {
+        let mut app = collision_app();
+        spawn_asteroid(&mut app, hanging);
+        app.world_mut().spawn((Player, square(touching, 16.)));
+
+        app.update();
+
+        let killed = app.world().resource::<Events<PlayerKilledEvent>>();
+        assert_eq!(killed.len(), 1, "{}: the ship survived", name);
+    }
+}
+
+#[test]
+fn projectile_hits_a_wrapped_asteroid_at_every_edge_and_corner() {
+    for (name, hanging, touching, _) in across_seams() {
+        let mut app = collision_app();
+        spawn_asteroid(&mut app, hanging);
+        app.world_mut().spawn((Projectile, square(touching, 16.)));
+
+        app.update();
+
+        let destroyed = app.world().resource::<Events<AsteroidDestroyedEvent>>();
+        assert_eq!(destroyed.len(), 1, "{}: the asteroid wasn't hit", name);
+    }
+}
+
+#[test]
+fn kill_boundary_wraps_the_ship_while_invulnerable() {
+    for invulnerable in [false, true] {
+        let mut app = App::new();
+        app.add_event::<PlayerKilledEvent>()
+            .init_resource::<Arena>()
+            .add_systems(Update, arena_boundary_system);
+
+        // wholly past the right edge, a little below the middle
+        let position = Vec2::new(half_arena().x + 8., -10.);
+        let mut ship = app.world_mut().spawn((
+            Player,
+            ArenaBoundary::Kill,
+            Transform::from_translation(position.extend(0.)),
+        ));
+        if invulnerable {
+            ship.insert(Invulnerable(Timer::from_seconds(1., TimerMode::Once)));
+        }
+        let ship = ship.id();
+
+        app.update();
+
+        let killed = app.world().resource::<Events<PlayerKilledEvent>>().len();
+        let translation = app.world().get::<Transform>(ship).unwrap().translation;
+        if invulnerable {
+            assert_eq!(killed, 0, "the invulnerable ship was killed");
+            assert_eq!(
+                translation.truncate(),
+                Vec2::new(-half_arena().x + 8., -10.),
+                "the invulnerable ship wasn't wrapped"
+            );
+        } else {
+            assert_eq!(killed, 1, "the ship survived leaving the arena");
+        }
+    }
+}